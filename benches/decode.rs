@@ -0,0 +1,91 @@
+// Decodes PUB/MSG batches with the control line parser the `simd` feature
+// picks, and runs the nom and memchr parsers side by side:
+//
+//   cargo bench --bench decode
+use bytes::{BufMut, Bytes, BytesMut};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+
+use nats_codec::{bench, Codec};
+
+const FRAMES: usize = 1024;
+
+fn frames(op: &str) -> BytesMut {
+    let mut input = BytesMut::new();
+
+    for i in 0..FRAMES {
+        let payload = format!("payload-{}", i);
+
+        let line = match op {
+            "PUB" => format!(
+                "PUB telemetry.sensor.{} _INBOX.{} {}\r\n",
+                i,
+                i,
+                payload.len()
+            ),
+            _ => format!(
+                "MSG telemetry.sensor.{} {} _INBOX.{} {}\r\n",
+                i,
+                i,
+                i,
+                payload.len()
+            ),
+        };
+
+        input.put_slice(line.as_bytes());
+        input.put_slice(payload.as_bytes());
+        input.put_slice(b"\r\n");
+    }
+
+    input
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode");
+
+    group.throughput(Throughput::Elements(FRAMES as u64));
+
+    for op in ["PUB", "MSG"] {
+        let input = frames(op);
+
+        group.bench_function(op, |b| {
+            b.iter_batched(
+                || (input.clone(), Vec::with_capacity(FRAMES)),
+                |(mut input, mut batch)| {
+                    Codec::new()
+                        .decode_batch(&mut input, &mut batch)
+                        .expect("ok");
+
+                    batch
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_control_line(c: &mut Criterion) {
+    let mut group = c.benchmark_group("control_line");
+
+    let lines = [
+        ("PUB", "PUB telemetry.sensor.42 _INBOX.42 10"),
+        ("MSG", "MSG telemetry.sensor.42 42 _INBOX.42 10"),
+    ];
+
+    for (op, line) in lines {
+        let line = Bytes::from_static(line.as_bytes());
+
+        group.bench_function(format!("{}/nom", op), |b| {
+            b.iter(|| bench::nom(line.clone()).expect("ok"))
+        });
+        group.bench_function(format!("{}/memchr", op), |b| {
+            b.iter(|| bench::memchr(line.clone()).expect("ok"))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode, bench_control_line);
+criterion_main!(benches);
@@ -0,0 +1,86 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use tokio_util::codec::Encoder;
+
+use nats_codec::message::publish;
+use nats_codec::{Codec, Message};
+
+const FRAMES: usize = 1024;
+
+fn publishes() -> Vec<Message> {
+    (0..FRAMES)
+        .map(|i| {
+            let payload = Bytes::from(format!("payload-{}", i));
+
+            Message::Publish(publish::Payload {
+                subject: Bytes::from("telemetry.sensor.temperature"),
+                reply_to: Some(Bytes::from(format!("_INBOX.{}", i))),
+                payload_size: payload.len(),
+                payload: Some(payload),
+            })
+        })
+        .collect()
+}
+
+// the encoder as it was before: `to_string` per integer, reactive growth
+fn encode_to_string(message: &Message, dst: &mut BytesMut) {
+    if let Message::Publish(p) = message {
+        dst.put_slice(b"PUB ");
+        dst.put_slice(&p.subject);
+
+        if let Some(reply_to) = &p.reply_to {
+            dst.put_slice(b" ");
+            dst.put_slice(reply_to);
+        }
+
+        dst.put_slice(b" ");
+        dst.put_slice(p.payload_size.to_string().as_bytes());
+        dst.put_slice(b"\r\n");
+
+        if let Some(payload) = &p.payload {
+            dst.put_slice(payload);
+        }
+        dst.put_slice(b"\r\n");
+    }
+}
+
+fn bench_publish(c: &mut Criterion) {
+    let messages = publishes();
+
+    let mut group = c.benchmark_group("encode_publish");
+
+    group.throughput(Throughput::Elements(FRAMES as u64));
+
+    group.bench_function("to_string", |b| {
+        b.iter_batched_ref(
+            BytesMut::new,
+            |dst| {
+                for message in &messages {
+                    encode_to_string(message, dst);
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("codec", |b| {
+        let mut codec = Codec::new();
+
+        b.iter_batched(
+            || (BytesMut::new(), messages.clone()),
+            |(mut dst, messages)| {
+                for message in messages {
+                    codec.encode(message, &mut dst).expect("ok");
+                }
+
+                dst
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_publish);
+criterion_main!(benches);
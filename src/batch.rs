@@ -0,0 +1,182 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::BytesMut;
+use futures::{ready, Stream};
+use tokio::io::AsyncRead;
+use tokio_util::io::poll_read_buf;
+
+use super::{codec::Codec, message::Message};
+
+const INITIAL_CAPACITY: usize = 8 * 1024;
+
+/// `Stream` yielding every message decoded from a single read as one batch.
+#[derive(Debug)]
+pub struct BatchStream<R> {
+    inner: R,
+    codec: Codec,
+    buf: BytesMut,
+    eof: bool,
+    // hit after the frames of a batch, returned by the next poll
+    error: Option<io::Error>,
+    // an error was returned, the buffered bytes would only repeat it
+    done: bool,
+}
+
+impl<R> BatchStream<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_codec(inner, Codec::new())
+    }
+
+    pub fn with_codec(inner: R, codec: Codec) -> Self {
+        Self {
+            inner,
+            codec,
+            buf: BytesMut::with_capacity(INITIAL_CAPACITY),
+            eof: false,
+            error: None,
+            done: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn fail(&mut self, e: io::Error) -> io::Error {
+        self.buf.clear();
+        self.done = true;
+
+        e
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for BatchStream<R> {
+    type Item = Result<Vec<Message>, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if let Some(e) = this.error.take() {
+            return Poll::Ready(Some(Err(this.fail(e))));
+        }
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            let mut batch = Vec::new();
+
+            if let Err(e) = this.codec.decode_batch(&mut this.buf, &mut batch) {
+                if batch.is_empty() {
+                    return Poll::Ready(Some(Err(this.fail(e))));
+                }
+
+                this.error = Some(e);
+            }
+
+            if !batch.is_empty() {
+                return Poll::Ready(Some(Ok(batch)));
+            }
+
+            if this.eof {
+                // the control line of a truncated MSG/HMSG may be consumed already
+                if this.buf.is_empty() && !this.codec.is_pending() {
+                    return Poll::Ready(None);
+                }
+
+                let kind = io::ErrorKind::UnexpectedEof;
+                let e = io::Error::new(kind, "bytes remaining on stream");
+
+                return Poll::Ready(Some(Err(this.fail(e))));
+            }
+
+            this.buf.reserve(INITIAL_CAPACITY);
+
+            if ready!(poll_read_buf(Pin::new(&mut this.inner), cx, &mut this.buf))? == 0 {
+                this.eof = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::BatchStream;
+    use crate::Message;
+
+    #[test]
+    fn test_batch_stream() {
+        let input = &b"PING\r\nMSG FOO 1 5\r\nhello\r\nPONG\r\n"[..];
+
+        let mut stream = BatchStream::new(input);
+
+        futures::executor::block_on(async {
+            let batch = stream.next().await.expect("some").expect("ok");
+
+            assert_eq!(3, batch.len());
+            assert_eq!(Message::Ping, batch[0]);
+            assert_eq!(Message::Pong, batch[2]);
+
+            assert!(stream.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn test_batch_stream_error() {
+        let input = &b"PING\r\nPONG\r\nFOO BAR\r\nPING\r\n"[..];
+
+        let mut stream = BatchStream::new(input);
+
+        futures::executor::block_on(async {
+            let batch = stream.next().await.expect("some").expect("ok");
+
+            assert_eq!(vec![Message::Ping, Message::Pong], batch);
+            assert!(stream.next().await.expect("some").is_err());
+        });
+    }
+
+    #[test]
+    fn test_batch_stream_eof() {
+        let input = &b"PING\r\nMSG FOO 1 5\r\nhel"[..];
+
+        let mut stream = BatchStream::new(input);
+
+        futures::executor::block_on(async {
+            assert_eq!(1, stream.next().await.expect("some").expect("ok").len());
+            assert!(stream.next().await.expect("some").is_err());
+            assert!(stream.next().await.is_none());
+        });
+
+        // the payload is missing entirely, nothing is left in the buffer
+        let mut stream = BatchStream::new(&b"PING\r\nMSG FOO 1 5\r\n"[..]);
+
+        futures::executor::block_on(async {
+            assert_eq!(1, stream.next().await.expect("some").expect("ok").len());
+
+            let err = stream.next().await.expect("some").expect_err("truncated");
+
+            assert_eq!(std::io::ErrorKind::UnexpectedEof, err.kind());
+            assert!(stream.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn test_batch_stream_fused() {
+        let input = &b"PUB FOO 3\r\nhello\r\nPING\r\n"[..];
+
+        let mut stream = BatchStream::new(input);
+
+        futures::executor::block_on(async {
+            assert!(stream.next().await.expect("some").is_err());
+            assert!(stream.next().await.is_none());
+        });
+    }
+}
@@ -1,17 +1,43 @@
+use bytes::{Buf, Bytes, BytesMut};
 use std::io;
-use bytes::Buf;
-use tokio_util;
+use std::sync::Arc;
 
-use super::{message::Message, parser};
+use super::{
+    error::ProtocolError,
+    message::{op::Op, Message},
+    metrics::{CodecMetrics, NoopMetrics},
+    parser::{self, ParseMode},
+};
 
-enum State {
+#[derive(Debug, Default)]
+pub(crate) enum State {
+    #[default]
     Message,
     Payload(usize),
 }
 
+pub(crate) enum Body {
+    Complete(Bytes),
+    Partial,
+    // no CRLF after <#bytes>
+    Mismatch,
+}
+
+#[derive(Debug)]
 pub struct Codec {
     state: State,
     message: Option<Message>,
+    // control line and CRLF of the message waiting for its payload
+    head: Bytes,
+    options: parser::Options,
+    recovery: bool,
+    // skipping to the next control line after a payload size mismatch
+    resync: bool,
+    invalid_frames: u64,
+    skipped_bytes: u64,
+    derive_sizes: bool,
+    metrics: Arc<dyn CodecMetrics>,
+    large_frame_threshold: usize,
 }
 
 impl Default for Codec {
@@ -19,6 +45,15 @@ impl Default for Codec {
         Self {
             state: State::Message,
             message: None,
+            head: Bytes::new(),
+            options: Default::default(),
+            recovery: false,
+            resync: false,
+            invalid_frames: 0,
+            skipped_bytes: 0,
+            derive_sizes: false,
+            metrics: Arc::new(NoopMetrics),
+            large_frame_threshold: usize::MAX,
         }
     }
 }
@@ -28,8 +63,179 @@ impl Codec {
         Self {
             state: State::Message,
             message: None,
+            head: Bytes::new(),
+            options: Default::default(),
+            recovery: false,
+            resync: false,
+            invalid_frames: 0,
+            skipped_bytes: 0,
+            derive_sizes: false,
+            metrics: Arc::new(NoopMetrics),
+            large_frame_threshold: usize::MAX,
+        }
+    }
+
+    /// Decodes every complete frame in `input` into `dst`, a trailing partial
+    /// frame is kept in `input` and resumed by the next call.
+    ///
+    /// Frames are still parsed one by one through `decode`, this only saves
+    /// the per frame round trip through `FramedRead`. On error the messages
+    /// decoded before the bad frame are left in `dst`.
+    ///
+    /// Returns the number of decoded messages.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(buffered = input.len()))
+    )]
+    pub fn decode_batch(
+        &mut self,
+        input: &mut bytes::BytesMut,
+        dst: &mut Vec<Message>,
+    ) -> Result<usize, io::Error> {
+        use tokio_util::codec::Decoder;
+
+        let before = dst.len();
+
+        while let Some(message) = self.decode(input)? {
+            dst.push(message);
+        }
+
+        Ok(dst.len() - before)
+    }
+
+    /// See [`ParseMode`], lenient by default.
+    pub fn with_parse_mode(mut self, mode: ParseMode) -> Self {
+        self.options.mode = mode;
+
+        self
+    }
+
+    /// Set the declared sizes of outgoing messages from their body instead
+    /// of rejecting mismatches.
+    pub fn with_derive_sizes(mut self, derive_sizes: bool) -> Self {
+        self.derive_sizes = derive_sizes;
+
+        self
+    }
+
+    /// Report frames and errors to `metrics`, see [`crate::Counters`].
+    pub fn with_metrics(mut self, metrics: Arc<dyn CodecMetrics>) -> Self {
+        self.metrics = metrics;
+
+        self
+    }
+
+    /// Report frames larger than `len` bytes, they're still decoded/encoded.
+    pub fn with_large_frame_threshold(mut self, len: usize) -> Self {
+        self.large_frame_threshold = len;
+
+        self
+    }
+
+    /// Report bad frames as `Message::Invalid` and skip to the next control
+    /// line instead of failing the stream.
+    pub fn with_recovery(mut self, recovery: bool) -> Self {
+        self.recovery = recovery;
+
+        self
+    }
+
+    /// Number of frames skipped in recovery mode.
+    pub fn invalid_frames(&self) -> u64 {
+        self.invalid_frames
+    }
+
+    /// Number of bytes skipped in recovery mode.
+    pub fn skipped_bytes(&self) -> u64 {
+        self.skipped_bytes
+    }
+
+    /// Yield INFO/CONNECT as `Message::RawInfo`/`Message::RawConnect` and
+    /// leave json decoding to the caller.
+    pub fn with_lazy_json(mut self, lazy_json: bool) -> Self {
+        self.options.lazy_json = lazy_json;
+
+        self
+    }
+}
+
+impl Codec {
+    // a control line was taken and its payload is still missing
+    pub(crate) fn is_pending(&self) -> bool {
+        matches!(self.state, State::Payload(_))
+    }
+
+    fn size_mismatch(&self, size: usize) -> ProtocolError {
+        let subject = self.message.as_ref().and_then(|m| m.subject());
+
+        ProtocolError::PayloadSizeMismatch {
+            subject: subject.cloned().unwrap_or_default(),
+            size,
+        }
+    }
+
+    // whole frame, CRLFs included
+    fn check_frame_len(&self, message: &Message, len: usize) {
+        if len <= self.large_frame_threshold {
+            return;
+        }
+
+        if let Some(op) = message.op() {
+            self.metrics.large_frame(op, len);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::warn!(message = %message, len, "large frame");
+    }
+
+    fn decoded(&self, message: &Message, len: usize) {
+        if let Some(op) = message.op() {
+            self.metrics.frame_decoded(op, len);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(message = %message.trace().body(64), "decoded");
+    }
+
+    fn decode_error(&self, reason: &ProtocolError) {
+        self.metrics.decode_error(reason);
+
+        #[cfg(feature = "tracing")]
+        match self.recovery {
+            true => tracing::debug!(%reason, "skipping invalid frame"),
+            false => tracing::warn!(%reason, "decode failed"),
+        }
+    }
+
+    fn partial_frame(&self, input: &BytesMut) {
+        // `Framed` calls `decode` on an empty buffer after every frame
+        if !input.is_empty() {
+            self.metrics.partial_frame();
         }
     }
+
+    // `raw` is the control line with its CRLF
+    fn invalid(&mut self, raw: Bytes, reason: ProtocolError) -> Message {
+        self.invalid_frames += 1;
+        self.skipped_bytes += raw.len() as u64;
+
+        Message::Invalid { raw, reason }
+    }
+
+    // drops the bytes up to the next control line, `false` until it's buffered
+    fn resync(&mut self, input: &mut BytesMut) -> bool {
+        let (skip, done) = match resync(input) {
+            Ok(at) => (at, true),
+            Err(at) => (at, false),
+        };
+
+        input.advance(skip);
+
+        self.skipped_bytes += skip as u64;
+        self.resync = !done;
+
+        done
+    }
 }
 
 impl tokio_util::codec::Decoder for Codec {
@@ -38,45 +244,97 @@ impl tokio_util::codec::Decoder for Codec {
     type Error = io::Error;
 
     fn decode(&mut self, input: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        self.metrics.buffered(input.len());
+
+        if self.resync && !self.resync(input) {
+            return Ok(None);
+        }
+
         loop {
             match self.state {
                 State::Message => {
                     // take control line and parse message
-                    let message = match parser::cl1(input) {
-                        Err(e) => {
-                            return into_result(e);
+                    let head = match parser::cl1(input) {
+                        Some(head) => head,
+                        // control line isn't complete yet
+                        None => {
+                            self.partial_frame(input);
+
+                            return Ok(None);
                         }
-                        Ok((_, input)) => match parser::parse(input) {
-                            Ok(message) => message,
-                            Err(e) => {
-                                return into_result(e);
+                    };
+
+                    let line = head.slice(..head.len() - 2);
+
+                    let message = match parser::parse_with(line.clone(), &self.options) {
+                        Ok(message) => message,
+                        Err(e) => {
+                            let reason = match into_result(e) {
+                                Err(reason) => reason,
+                                Ok(_) => return Ok(None),
+                            };
+
+                            self.decode_error(&reason);
+
+                            if !self.recovery {
+                                return Err(reason.into());
                             }
-                        },
+
+                            break Ok(Some(self.invalid(head, reason)));
+                        }
                     };
 
                     if !message.with_body() {
+                        self.decoded(&message, head.len());
+
                         break Ok(Some(message));
                     } else {
-                        self.state = State::Payload(message.body_size().unwrap());
+                        let size = message.body_size().unwrap();
+
+                        self.check_frame_len(&message, frame_len(&head, size));
+                        self.state = State::Payload(size);
 
                         self.message = Some(message);
+                        self.head = head;
                     }
                 }
                 State::Payload(size) => {
-                    break if input.len() >= size + 2 {
-                        let body = input.split_to(size).freeze();
+                    break match take_body(input, size) {
+                        Body::Complete(body) => {
+                            let mut message = self.message.take().unwrap();
 
-                        input.advance(2);
+                            message.set_body(body);
 
-                        let mut message = self.message.take().unwrap();
+                            self.state = State::Message;
+                            self.decoded(&message, frame_len(&self.head, size));
 
-                        message.set_body(body);
+                            Ok(Some(message))
+                        }
+                        Body::Partial => {
+                            self.partial_frame(input);
 
-                        self.state = State::Message;
+                            Ok(None)
+                        }
+                        Body::Mismatch => {
+                            let reason = self.size_mismatch(size);
 
-                        Ok(Some(message))
-                    } else {
-                        Ok(None)
+                            self.decode_error(&reason);
+
+                            if !self.recovery {
+                                return Err(reason.into());
+                            }
+
+                            let head = std::mem::take(&mut self.head);
+
+                            self.message = None;
+                            self.state = State::Message;
+
+                            // the payload may hold CRLFs too, so its end is unknown
+                            // and the next frame is found by its op instead
+                            self.resync = true;
+
+                            Ok(Some(self.invalid(head, reason)))
+                        }
                     };
                 }
             }
@@ -87,20 +345,100 @@ impl tokio_util::codec::Decoder for Codec {
 impl tokio_util::codec::Encoder<Message> for Codec {
     type Error = io::Error;
 
-    fn encode(&mut self, item: Message, dst: &mut bytes::BytesMut) -> Result<(), Self::Error> {
-        item.encode(dst)
+    fn encode(&mut self, mut item: Message, dst: &mut bytes::BytesMut) -> Result<(), Self::Error> {
+        if self.derive_sizes {
+            item.derive_sizes();
+        }
+
+        // never put a frame on the wire the other side can't parse
+        if let Err(reason) = item.validate() {
+            self.metrics.encode_error(&reason);
+
+            #[cfg(feature = "tracing")]
+            tracing::warn!(message = %item, %reason, "encode failed");
+
+            return Err(reason.into());
+        }
+
+        let start = dst.len();
+
+        // measuring INFO/CONNECT json means serializing it twice, let `dst` grow instead
+        if !matches!(item, Message::Info(_) | Message::Connect(_)) {
+            dst.reserve(item.encoded_len());
+        }
+
+        item.encode(dst)?;
+
+        let len = dst.len() - start;
+
+        self.check_frame_len(&item, len);
+
+        if let Some(op) = item.op() {
+            self.metrics.frame_encoded(op, len);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!(message = %item.trace().body(64), "encoded");
+
+        Ok(())
     }
 }
 
-fn into_result<T>(e: nom::Err<nom::error::Error<T>>) -> Result<Option<Message>, io::Error> {
-    match e {
-        nom::Err::Error(_e) => {
-            let kind = io::ErrorKind::Other;
+/// Takes the `size` byte payload and its CRLF off `input` once complete.
+#[inline]
+pub(crate) fn take_body(input: &mut BytesMut, size: usize) -> Body {
+    // `size + 2` may overflow for sizes close to usize::MAX
+    if input.len() < 2 || input.len() - 2 < size {
+        return Body::Partial;
+    }
+
+    // a wrong <#bytes> would desync every following frame
+    if &input[size..size + 2] != b"\r\n" {
+        return Body::Mismatch;
+    }
+
+    let body = input.split_to(size).freeze();
+
+    input.advance(2);
+
+    Body::Complete(body)
+}
+
+#[inline]
+fn frame_len(head: &[u8], size: usize) -> usize {
+    head.len().saturating_add(size).saturating_add(2)
+}
 
-            Err(io::Error::new(kind, "into_result"))
+// `Ok` with the offset of the next line starting with a known op, `Err` with
+// the number of bytes that can be dropped while none is buffered yet
+fn resync(input: &[u8]) -> Result<usize, usize> {
+    // longest op, CONNECT
+    const MAX_OP_LEN: usize = 7;
+
+    let mut at = 0;
+
+    // the skipped payload itself is never taken for a frame
+    while let Some(idx) = memchr::memmem::find(&input[at..], b"\r\n") {
+        let start = at + idx + 2;
+        let line = &input[start..];
+
+        match memchr::memchr3(b' ', b'\t', b'\r', line) {
+            Some(end) if Op::try_from(&line[..end]).is_ok() => return Ok(start),
+            // keep the CRLF, the op isn't complete yet
+            None if line.len() <= MAX_OP_LEN => return Err(at + idx),
+            _ => at = start,
         }
-        nom::Err::Failure(_) => Ok(None),
-        nom::Err::Incomplete(_) => Ok(None),
+    }
+
+    // a trailing CR may be the start of the next CRLF
+    Err(input.len().saturating_sub(1))
+}
+
+// `Ok` when the parser needs more input
+fn into_result(e: nom::Err<parser::Error>) -> Result<(), ProtocolError> {
+    match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => Err(e.into()),
+        nom::Err::Incomplete(_) => Ok(()),
     }
 }
 
@@ -110,6 +448,7 @@ mod tests {
     use tokio_util::codec::Decoder;
 
     use super::Codec;
+    use crate::{Message, ProtocolError};
 
     #[test]
     fn it_works() {
@@ -124,4 +463,309 @@ mod tests {
         println!("input: {:?}", input.len());
         println!("capacity: {:?}", input.capacity());
     }
+
+    #[test]
+    fn test_lazy_json() {
+        use crate::message::connect::Payload;
+
+        let mut input = BytesMut::from(
+            "CONNECT {\"verbose\":true,\"pedantic\":false,\"tls_required\":false,\"lang\":\"rust\",\"name\":\"\",\"version\":\"1\"}\r\nPING\r\n",
+        );
+
+        let mut codec = Codec::new().with_lazy_json(true);
+
+        let json = match codec.decode(&mut input).expect("ok") {
+            Some(Message::RawConnect(json)) => json,
+            m => panic!("unexpected {:?}", m),
+        };
+
+        let payload: Payload = json.decode().expect("ok");
+
+        assert!(payload.verbose);
+        assert_eq!("rust", payload.lang);
+
+        assert_eq!(Some(Message::Ping), codec.decode(&mut input).expect("ok"));
+    }
+
+    #[test]
+    fn test_decode_batch() {
+        let mut input = BytesMut::from(
+            "PING\r\nMSG FOO 1 5\r\nhello\r\nPUB BAR 3\r\nabc\r\nMSG FOO 1 5\r\nwor".as_bytes(),
+        );
+
+        let mut codec = Codec::new();
+        let mut batch = vec![];
+
+        assert_eq!(3, codec.decode_batch(&mut input, &mut batch).expect("ok"));
+        assert_eq!(Message::Ping, batch[0]);
+
+        // the partial MSG waits for its payload
+        assert_eq!(&b"wor"[..], &input[..]);
+
+        input.extend_from_slice(b"ld\r\nPONG\r\n");
+
+        assert_eq!(2, codec.decode_batch(&mut input, &mut batch).expect("ok"));
+        assert_eq!(Some(&bytes::Bytes::from("world")), batch[3].body());
+        assert_eq!(Message::Pong, batch[4]);
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_decode_hmessage() {
+        let mut input = BytesMut::from(
+            "HMSG FOO.BAR 9 BAZ.69 34 45\r\nNATS/1.0\r\nFoodGroup: vegetable\r\n\r\nHello World\r\n",
+        );
+
+        let mut codec = Codec::new();
+
+        let message = match codec.decode(&mut input).expect("ok") {
+            Some(Message::HMessage(message)) => message,
+            m => panic!("unexpected {:?}", m),
+        };
+
+        assert_eq!(
+            Some(bytes::Bytes::from(
+                "NATS/1.0\r\nFoodGroup: vegetable\r\n\r\n"
+            )),
+            message.headers
+        );
+        assert_eq!(Some(bytes::Bytes::from("Hello World")), message.payload);
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_decode_overflow() {
+        let mut input = BytesMut::from("PUB foo 99999999999999999999999\r\n");
+
+        let err = Codec::new().decode(&mut input).expect_err("overflow");
+
+        assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+        assert_eq!(
+            Some(&ProtocolError::NumberOverflow),
+            err.get_ref()
+                .and_then(|e| e.downcast_ref::<ProtocolError>())
+        );
+    }
+
+    #[test]
+    fn test_payload_size_mismatch() {
+        for raw in &[
+            "PUB FOO 5\r\nhello!!\r\n",
+            "MSG FOO 1 7\r\nhello\r\nPING\r\n",
+        ] {
+            let mut input = BytesMut::from(*raw);
+
+            let err = Codec::new().decode(&mut input).expect_err("mismatch");
+
+            let size = if raw.starts_with("PUB") { 5 } else { 7 };
+
+            assert_eq!(std::io::ErrorKind::InvalidData, err.kind());
+            assert_eq!(
+                Some(&ProtocolError::PayloadSizeMismatch {
+                    subject: bytes::Bytes::from("FOO"),
+                    size,
+                }),
+                err.get_ref()
+                    .and_then(|e| e.downcast_ref::<ProtocolError>())
+            );
+            assert_eq!(
+                format!("payload size mismatch: {} bytes declared for 'FOO'", size),
+                err.to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_recovery() {
+        let mut input = BytesMut::from(
+            "PING\r\nFOO BAR\r\nPUB FOO 3\r\nhello\r\nPUB FOO 99999999999999999999999\r\nPONG\r\n",
+        );
+
+        let mut codec = Codec::new().with_recovery(true);
+        let mut batch = vec![];
+
+        assert_eq!(5, codec.decode_batch(&mut input, &mut batch).expect("ok"));
+
+        assert_eq!(Message::Ping, batch[0]);
+        assert_eq!(
+            Message::Invalid {
+                raw: bytes::Bytes::from("FOO BAR\r\n"),
+                reason: ProtocolError::InvalidControlLine,
+            },
+            batch[1]
+        );
+        assert_eq!(
+            Message::Invalid {
+                raw: bytes::Bytes::from("PUB FOO 3\r\n"),
+                reason: ProtocolError::PayloadSizeMismatch {
+                    subject: bytes::Bytes::from("FOO"),
+                    size: 3,
+                },
+            },
+            batch[2]
+        );
+        assert_eq!(
+            Message::Invalid {
+                raw: bytes::Bytes::from("PUB FOO 99999999999999999999999\r\n"),
+                reason: ProtocolError::NumberOverflow,
+            },
+            batch[3]
+        );
+        assert_eq!(Message::Pong, batch[4]);
+
+        assert_eq!(3, codec.invalid_frames());
+        assert_eq!(9 + 18 + 33, codec.skipped_bytes());
+    }
+
+    #[test]
+    fn test_recovery_resync() {
+        // sizes don't match the header block, which holds CRLFs like the payload
+        let mut input =
+            BytesMut::from("HMSG FOO 1 12 15\r\nNATS/1.0\r\nA: B\r\n\r\nhello\r\nworld\r\nPI");
+
+        let mut codec = Codec::new().with_recovery(true);
+
+        match codec.decode(&mut input).expect("ok") {
+            Some(Message::Invalid { raw, .. }) => assert_eq!("HMSG FOO 1 12 15\r\n", raw),
+            m => panic!("unexpected {:?}", m),
+        }
+
+        // nothing in the header block or payload is taken for a frame
+        assert_eq!(None, codec.decode(&mut input).expect("ok"));
+
+        input.extend_from_slice(b"NG\r\nPUB BAR 2\r\nok\r\n");
+
+        assert_eq!(Some(Message::Ping), codec.decode(&mut input).expect("ok"));
+        assert_eq!(
+            Some(Message::publish("BAR", "ok")),
+            codec.decode(&mut input).expect("ok")
+        );
+        assert!(input.is_empty());
+
+        assert_eq!(1, codec.invalid_frames());
+        assert_eq!(18 + 32, codec.skipped_bytes());
+    }
+
+    #[test]
+    fn test_encode_validation() {
+        use crate::message::publish::Payload;
+        use tokio_util::codec::Encoder;
+
+        let message = Message::Publish(Payload {
+            subject: bytes::Bytes::from("FOO"),
+            reply_to: None,
+            payload_size: 0,
+            payload: Some(bytes::Bytes::from("hello")),
+        });
+
+        let mut dst = BytesMut::new();
+
+        let err = Codec::new()
+            .encode(message.clone(), &mut dst)
+            .expect_err("size mismatch");
+
+        assert_eq!(
+            Some(&ProtocolError::BodySizeMismatch {
+                declared: 0,
+                actual: 5
+            }),
+            err.get_ref()
+                .and_then(|e| e.downcast_ref::<ProtocolError>())
+        );
+        assert!(dst.is_empty());
+
+        Codec::new()
+            .with_derive_sizes(true)
+            .encode(message, &mut dst)
+            .expect("ok");
+
+        assert_eq!(&b"PUB FOO 5\r\nhello\r\n"[..], &dst[..]);
+    }
+
+    #[test]
+    fn test_metrics() {
+        use crate::message::op::Op;
+        use crate::Counters;
+        use std::sync::Arc;
+        use tokio_util::codec::{Decoder, Encoder};
+
+        let counters = Arc::new(Counters::new());
+        let mut codec = Codec::new()
+            .with_metrics(counters.clone())
+            .with_large_frame_threshold(16);
+
+        let mut buf = BytesMut::from(&b"PING\r\nMSG FOO 1 5\r\nhel"[..]);
+
+        assert_eq!(Some(Message::Ping), codec.decode(&mut buf).unwrap());
+        assert_eq!(None, codec.decode(&mut buf).unwrap());
+
+        buf.extend_from_slice(b"lo\r\nMSG FOO 1 x\r\n");
+
+        assert!(codec.decode(&mut buf).unwrap().is_some());
+        assert!(codec.decode(&mut buf).is_err());
+
+        let mut dst = BytesMut::new();
+
+        codec
+            .encode(Message::publish("FOO", "hello"), &mut dst)
+            .unwrap();
+        assert!(codec
+            .encode(Message::publish("", "hello"), &mut dst)
+            .is_err());
+
+        assert_eq!(1, counters.frames_in(Op::Ping));
+        assert_eq!(6, counters.bytes_in(Op::Ping));
+        assert_eq!(1, counters.frames_in(Op::Message));
+        assert_eq!(20, counters.bytes_in(Op::Message));
+        assert_eq!(1, counters.partial_frames());
+        assert_eq!(1, counters.decode_errors());
+        assert_eq!(1, counters.frames_out(Op::Publish));
+        assert_eq!(dst.len() as u64, counters.bytes_out(Op::Publish));
+        assert_eq!(1, counters.encode_errors());
+        assert_eq!(22, counters.buffer_high_water());
+        assert!(counters
+            .render("nats")
+            .contains("nats_large_frames_total{op=\"MSG\"} 1\n"));
+    }
+
+    #[test]
+    fn test_metrics_recovery() {
+        use crate::Counters;
+        use std::sync::Arc;
+
+        let counters = Arc::new(Counters::new());
+        let mut codec = Codec::new()
+            .with_metrics(counters.clone())
+            .with_recovery(true);
+
+        let mut buf = BytesMut::from(&b"PUB FOO 3\r\nhel"[..]);
+
+        assert_eq!(None, codec.decode(&mut buf).unwrap());
+
+        buf.extend_from_slice(b"lo\r\nwor");
+
+        assert!(matches!(
+            codec.decode(&mut buf).unwrap(),
+            Some(Message::Invalid { .. })
+        ));
+
+        // every read while resyncing drops bytes, none is an error or a partial frame
+        assert_eq!(None, codec.decode(&mut buf).unwrap());
+
+        buf.extend_from_slice(b"ld\r\n");
+
+        assert_eq!(None, codec.decode(&mut buf).unwrap());
+
+        buf.extend_from_slice(b"PI");
+
+        assert_eq!(None, codec.decode(&mut buf).unwrap());
+
+        buf.extend_from_slice(b"NG\r\n");
+
+        assert_eq!(Some(Message::Ping), codec.decode(&mut buf).unwrap());
+
+        assert_eq!(1, counters.decode_errors());
+        assert_eq!(1, counters.partial_frames());
+        assert_eq!(1, codec.invalid_frames());
+    }
 }
@@ -0,0 +1,115 @@
+use std::{error, fmt, io};
+
+use bytes::Bytes;
+
+#[cfg(feature = "serde")]
+use crate::message::repr;
+
+/// Protocol violation detected by the codec.
+///
+/// Surfaced as the inner error of an `io::ErrorKind::InvalidData` error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ProtocolError {
+    // control line doesn't match the protocol grammar
+    InvalidControlLine,
+    // sid, size or max_msgs doesn't fit into usize
+    NumberOverflow,
+    // payload isn't followed by CRLF, the declared size is wrong
+    PayloadSizeMismatch {
+        #[cfg_attr(feature = "serde", serde(with = "repr::bytes"))]
+        subject: Bytes,
+        size: usize,
+    },
+    // HPUB/HMSG header size is larger than the total size
+    HeaderSizeExceedsTotal {
+        header_size: usize,
+        total_size: usize,
+    },
+    // encoder: empty or containing whitespace
+    InvalidSubject(#[cfg_attr(feature = "serde", serde(with = "repr::bytes"))] Bytes),
+    InvalidReplyTo(#[cfg_attr(feature = "serde", serde(with = "repr::bytes"))] Bytes),
+    InvalidQueueGroup(#[cfg_attr(feature = "serde", serde(with = "repr::bytes"))] Bytes),
+    // encoder: declared size doesn't match the body (headers included for HPUB/HMSG)
+    BodySizeMismatch {
+        declared: usize,
+        actual: usize,
+    },
+    // HPUB/HMSG header block that doesn't parse
+    InvalidHeaders(#[cfg_attr(feature = "serde", serde(with = "repr::bytes"))] Bytes),
+    // encoder: declared HPUB/HMSG header size doesn't match the headers
+    HeaderSizeMismatch {
+        declared: usize,
+        actual: usize,
+    },
+    // encoder: -ERR text containing a quote or line break
+    InvalidErrorText(String),
+    // encoder: raw INFO/CONNECT json containing a line break
+    InvalidJson(#[cfg_attr(feature = "serde", serde(with = "repr::bytes"))] Bytes),
+    // encoder: frames skipped by a recovering codec aren't sent on
+    InvalidFrame,
+    // encoder: header name or value that would break the header block
+    InvalidHeader(String),
+    // encoder: headers for a message without a body
+    UnexpectedHeaders,
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidControlLine => write!(f, "invalid control line"),
+            Self::NumberOverflow => write!(f, "number overflow"),
+            Self::PayloadSizeMismatch { subject, size } => write!(
+                f,
+                "payload size mismatch: {} bytes declared for '{}'",
+                size,
+                String::from_utf8_lossy(subject)
+            ),
+            Self::HeaderSizeExceedsTotal {
+                header_size,
+                total_size,
+            } => write!(
+                f,
+                "header size {} exceeds total size {}",
+                header_size, total_size
+            ),
+            Self::InvalidSubject(s) => {
+                write!(f, "invalid subject '{}'", String::from_utf8_lossy(s))
+            }
+            Self::InvalidReplyTo(s) => {
+                write!(f, "invalid reply subject '{}'", String::from_utf8_lossy(s))
+            }
+            Self::InvalidQueueGroup(s) => {
+                write!(f, "invalid queue group '{}'", String::from_utf8_lossy(s))
+            }
+            Self::InvalidHeaders(_) => write!(f, "invalid header block"),
+            Self::BodySizeMismatch { declared, actual } => write!(
+                f,
+                "body size mismatch: {} bytes declared, {} bytes given",
+                declared, actual
+            ),
+            Self::HeaderSizeMismatch { declared, actual } => write!(
+                f,
+                "header size mismatch: {} bytes declared, {} bytes given",
+                declared, actual
+            ),
+            Self::InvalidErrorText(s) => write!(f, "invalid error text '{}'", s),
+            Self::InvalidJson(_) => write!(f, "invalid json"),
+            Self::InvalidFrame => write!(f, "invalid frame"),
+            Self::InvalidHeader(name) => write!(f, "invalid header '{}'", name),
+            Self::UnexpectedHeaders => write!(f, "headers on a message without body"),
+        }
+    }
+}
+
+impl error::Error for ProtocolError {}
+
+impl From<ProtocolError> for io::Error {
+    fn from(e: ProtocolError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
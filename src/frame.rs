@@ -0,0 +1,357 @@
+use std::convert::TryFrom;
+use std::ops::Range;
+
+use bytes::Bytes;
+use memchr::{memchr, memchr2};
+
+use super::error::ProtocolError;
+use super::message::op::Op;
+use super::tokenizer::{self, parse_usize};
+
+/// Borrowed view of a single frame at the head of the read buffer.
+///
+/// All fields point into the buffer passed to [`decode_frame`], nothing is
+/// copied and the buffer is not advanced, so the frame can be inspected and
+/// then forwarded as `input[..frame.len]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame<'a> {
+    pub op: Op,
+    pub subject: Option<&'a [u8]>,
+    pub sid: Option<&'a [u8]>,
+    pub reply_to: Option<&'a [u8]>,
+    pub queue_group: Option<&'a [u8]>,
+    pub max_messages: Option<&'a [u8]>,
+    pub header_size: Option<usize>,
+    // #bytes of the control line, for HPUB/HMSG the total size headers included
+    pub payload_size: Option<usize>,
+    // everything after the op: INFO/CONNECT json, -ERR text, raw arguments
+    pub args: &'a [u8],
+    // HPUB/HMSG header block position relative to the start of the buffer
+    pub headers: Option<Range<usize>>,
+    // body position relative to the start of the buffer, headers excluded
+    pub body: Option<Range<usize>>,
+    // total length of the frame including the trailing CRLF(s)
+    pub len: usize,
+    input: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    #[inline]
+    pub fn payload(&self) -> Option<&'a [u8]> {
+        self.body.clone().map(|range| &self.input[range])
+    }
+
+    #[inline]
+    pub fn header_block(&self) -> Option<&'a [u8]> {
+        self.headers.clone().map(|range| &self.input[range])
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &'a [u8] {
+        &self.input[..self.len]
+    }
+}
+
+/// Decodes the frame at the head of `input` without allocating or advancing.
+///
+/// Returns `Ok(None)` when the buffer doesn't hold a complete frame yet.
+pub fn decode_frame(input: &[u8]) -> Result<Option<Frame<'_>>, ProtocolError> {
+    let invalid = ProtocolError::InvalidControlLine;
+
+    let cl_len = match control_line(input)? {
+        Some(idx) => idx,
+        None => return Ok(None),
+    };
+
+    let cl = &input[..cl_len];
+
+    let (raw_op, args) = match memchr2(b' ', b'\t', cl) {
+        Some(idx) => (&cl[..idx], tokenizer::trim_start(&cl[idx..])),
+        None => (cl, &cl[cl.len()..]),
+    };
+
+    let op = Op::try_from(raw_op).map_err(|_| invalid.clone())?;
+
+    let mut frame = Frame {
+        op,
+        subject: None,
+        sid: None,
+        reply_to: None,
+        queue_group: None,
+        max_messages: None,
+        header_size: None,
+        payload_size: None,
+        args,
+        headers: None,
+        body: None,
+        len: cl_len + 2,
+        input,
+    };
+
+    // json and error text aren't split into fields
+    if matches!(
+        op,
+        Op::Ok | Op::Err | Op::Info | Op::Ping | Op::Pong | Op::Connect
+    ) {
+        return Ok(Some(frame));
+    }
+
+    let mut fields = [&[][..]; 5];
+    let n = tokenizer::split(args, &mut fields).ok_or(invalid.clone())?;
+
+    match (op, &fields[..n]) {
+        // PUB <subject> [reply-to] <#bytes>
+        (Op::Publish, &[subject, size]) => {
+            frame.subject = Some(subject);
+            frame.payload_size = Some(parse_usize(size)?);
+        }
+        (Op::Publish, &[subject, reply_to, size]) => {
+            frame.subject = Some(subject);
+            frame.reply_to = Some(reply_to);
+            frame.payload_size = Some(parse_usize(size)?);
+        }
+        // MSG <subject> <sid> [reply-to] <#bytes>
+        (Op::Message, &[subject, sid, size]) => {
+            frame.subject = Some(subject);
+            frame.sid = Some(sid);
+            frame.payload_size = Some(parse_usize(size)?);
+        }
+        (Op::Message, &[subject, sid, reply_to, size]) => {
+            frame.subject = Some(subject);
+            frame.sid = Some(sid);
+            frame.reply_to = Some(reply_to);
+            frame.payload_size = Some(parse_usize(size)?);
+        }
+        // HPUB <subject> [reply-to] <#header bytes> <#total bytes>
+        (Op::HPublish, &[subject, header_size, size]) => {
+            frame.subject = Some(subject);
+            frame.header_size = Some(parse_usize(header_size)?);
+            frame.payload_size = Some(parse_usize(size)?);
+        }
+        (Op::HPublish, &[subject, reply_to, header_size, size]) => {
+            frame.subject = Some(subject);
+            frame.reply_to = Some(reply_to);
+            frame.header_size = Some(parse_usize(header_size)?);
+            frame.payload_size = Some(parse_usize(size)?);
+        }
+        // HMSG <subject> <sid> [reply-to] <#header bytes> <#total bytes>
+        (Op::HMessage, &[subject, sid, header_size, size]) => {
+            frame.subject = Some(subject);
+            frame.sid = Some(sid);
+            frame.header_size = Some(parse_usize(header_size)?);
+            frame.payload_size = Some(parse_usize(size)?);
+        }
+        (Op::HMessage, &[subject, sid, reply_to, header_size, size]) => {
+            frame.subject = Some(subject);
+            frame.sid = Some(sid);
+            frame.reply_to = Some(reply_to);
+            frame.header_size = Some(parse_usize(header_size)?);
+            frame.payload_size = Some(parse_usize(size)?);
+        }
+        // SUB <subject> [queue group] <sid>
+        (Op::Subscribe, &[subject, sid]) => {
+            frame.subject = Some(subject);
+            frame.sid = Some(sid);
+        }
+        (Op::Subscribe, &[subject, queue_group, sid]) => {
+            frame.subject = Some(subject);
+            frame.queue_group = Some(queue_group);
+            frame.sid = Some(sid);
+        }
+        // UNSUB <sid> [max_msgs]
+        (Op::Unsubscribe, &[sid]) => frame.sid = Some(sid),
+        (Op::Unsubscribe, &[sid, max_messages]) => {
+            frame.sid = Some(sid);
+            frame.max_messages = Some(max_messages);
+        }
+        _ => return Err(invalid),
+    };
+
+    if let Some(size) = frame.payload_size {
+        let header_size = frame.header_size.unwrap_or(0);
+
+        if header_size > size {
+            return Err(ProtocolError::HeaderSizeExceedsTotal {
+                header_size,
+                total_size: size,
+            });
+        }
+
+        let start = frame.len;
+
+        // checked, the size may be anything up to usize::MAX
+        let end = match start.checked_add(size).and_then(|end| end.checked_add(2)) {
+            Some(end) => end,
+            None => return Err(ProtocolError::NumberOverflow),
+        };
+
+        if input.len() < end {
+            return Ok(None);
+        }
+
+        if &input[end - 2..end] != b"\r\n" {
+            return Err(ProtocolError::PayloadSizeMismatch {
+                subject: Bytes::copy_from_slice(frame.subject.unwrap_or_default()),
+                size,
+            });
+        }
+
+        if frame.header_size.is_some() {
+            frame.headers = Some(start..start + header_size);
+        }
+
+        frame.body = Some(start + header_size..start + size);
+        frame.len = end;
+    }
+
+    Ok(Some(frame))
+}
+
+// `None` until the CRLF is buffered, a CR followed by anything else can't
+// start one
+#[inline]
+fn control_line(input: &[u8]) -> Result<Option<usize>, ProtocolError> {
+    let idx = match memchr(b'\r', input) {
+        Some(idx) => idx,
+        None => return Ok(None),
+    };
+
+    match input.get(idx + 1) {
+        Some(b'\n') => Ok(Some(idx)),
+        Some(_) => Err(ProtocolError::InvalidControlLine),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::{decode_frame, Op, ProtocolError};
+
+    #[test]
+    fn test_decode_message() {
+        let input = BytesMut::from("MSG FOO.BAR 9 INBOX.34 11\r\nHello World\r\nPING\r\n");
+
+        let frame = decode_frame(&input).expect("ok").expect("complete");
+
+        assert_eq!(Op::Message, frame.op);
+        assert_eq!(Some(&b"FOO.BAR"[..]), frame.subject);
+        assert_eq!(Some(&b"9"[..]), frame.sid);
+        assert_eq!(Some(&b"INBOX.34"[..]), frame.reply_to);
+        assert_eq!(Some(11), frame.payload_size);
+        assert_eq!(Some(27..38), frame.body);
+        assert_eq!(Some(&b"Hello World"[..]), frame.payload());
+        assert_eq!(
+            &b"MSG FOO.BAR 9 INBOX.34 11\r\nHello World\r\n"[..],
+            frame.as_bytes()
+        );
+
+        // input is untouched
+        assert_eq!(46, input.len());
+
+        let frame = decode_frame(&input[frame.len..])
+            .expect("ok")
+            .expect("complete");
+
+        assert_eq!(Op::Ping, frame.op);
+        assert_eq!(6, frame.len);
+    }
+
+    #[test]
+    fn test_decode_publish() {
+        let input = BytesMut::from("PUB FRONT.DOOR 5\r\nKNOCK\r\n");
+
+        let frame = decode_frame(&input).expect("ok").expect("complete");
+
+        assert_eq!(Op::Publish, frame.op);
+        assert_eq!(Some(&b"FRONT.DOOR"[..]), frame.subject);
+        assert_eq!(None, frame.reply_to);
+        assert_eq!(Some(&b"KNOCK"[..]), frame.payload());
+        assert_eq!(input.len(), frame.len);
+    }
+
+    #[test]
+    fn test_decode_hmessage() {
+        let input = BytesMut::from("HMSG FOO 9 BAR 12 17\r\nNATS/1.0\r\n\r\nhello\r\n");
+
+        let frame = decode_frame(&input).expect("ok").expect("complete");
+
+        assert_eq!(Op::HMessage, frame.op);
+        assert_eq!(Some(&b"BAR"[..]), frame.reply_to);
+        assert_eq!(Some(12), frame.header_size);
+        assert_eq!(Some(&b"NATS/1.0\r\n\r\n"[..]), frame.header_block());
+        assert_eq!(Some(&b"hello"[..]), frame.payload());
+        assert_eq!(input.len(), frame.len);
+    }
+
+    #[test]
+    fn test_decode_bodyless() {
+        let input = BytesMut::from("SUB BAR G1 44\r\n");
+        let frame = decode_frame(&input).expect("ok").expect("complete");
+
+        assert_eq!(Op::Subscribe, frame.op);
+        assert_eq!(Some(&b"BAR"[..]), frame.subject);
+        assert_eq!(Some(&b"G1"[..]), frame.queue_group);
+        assert_eq!(Some(&b"44"[..]), frame.sid);
+        assert_eq!(None, frame.body);
+
+        let input = BytesMut::from("UNSUB 1 5\r\n");
+        let frame = decode_frame(&input).expect("ok").expect("complete");
+
+        assert_eq!(Some(&b"1"[..]), frame.sid);
+        assert_eq!(Some(&b"5"[..]), frame.max_messages);
+
+        let input = BytesMut::from("INFO {\"server_id\":\"x\"}\r\n");
+        let frame = decode_frame(&input).expect("ok").expect("complete");
+
+        assert_eq!(Op::Info, frame.op);
+        assert_eq!(&b"{\"server_id\":\"x\"}"[..], frame.args);
+    }
+
+    #[test]
+    fn test_decode_incomplete() {
+        for raw in &[
+            "",
+            "MSG FOO.BAR 9 11",
+            "MSG FOO.BAR 9 11\r",
+            "MSG FOO.BAR 9 11\r\nHello",
+        ] {
+            let input = BytesMut::from(*raw);
+
+            assert_eq!(None, decode_frame(&input).expect("ok"));
+        }
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        for raw in &[
+            "PUB FOO 99999999999999999999999\r\n",
+            "PUB FOO 18446744073709551615\r\n",
+            "HPUB FOO 12 10\r\n",
+            "PUB FOO 3\r\nhello\r\n",
+            "FOO BAR\r\n",
+            "PUB FOO\r\n",
+            "PUB FOO BAR BAZ 1\r\n",
+            "MSG FOO 1 x\r\n",
+        ] {
+            let input = BytesMut::from(*raw);
+
+            assert!(decode_frame(&input).is_err());
+        }
+
+        // a bare CR is rejected instead of waiting for a CRLF
+        assert_eq!(
+            Err(ProtocolError::InvalidControlLine),
+            decode_frame(b"PUB a\rb 1\r\n")
+        );
+        assert_eq!(
+            Err(ProtocolError::InvalidControlLine),
+            decode_frame(b"PUB FOO 11x\r\n")
+        );
+        assert_eq!(
+            Err(ProtocolError::NumberOverflow),
+            decode_frame(b"PUB FOO 99999999999999999999999\r\n")
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Error, Request};
+
+/// Account limits, `-1` is unlimited.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    pub max_memory: i64,
+    pub max_storage: i64,
+    pub max_streams: i64,
+    pub max_consumers: i64,
+    pub max_ack_pending: i64,
+    pub memory_max_stream_bytes: i64,
+    pub storage_max_stream_bytes: i64,
+    pub max_bytes_required: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiStats {
+    pub total: u64,
+    pub errors: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Info {
+    pub memory: u64,
+    pub storage: u64,
+    pub streams: usize,
+    pub consumers: usize,
+    pub limits: Limits,
+    pub api: ApiStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+}
+
+/// `$JS.API.INFO`
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct InfoRequest {}
+
+impl Request for InfoRequest {
+    type Response = Info;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok("INFO".to_string())
+    }
+}
+
+#[test]
+fn test_info() {
+    use super::{response, Error, ErrorCode};
+
+    let info: Info = response(
+        br#"{"type":"io.nats.jetstream.api.v1.account_info_response","memory":1024,"storage":2048,"streams":2,"consumers":3,"limits":{"max_memory":-1,"max_storage":-1,"max_streams":-1,"max_consumers":-1,"max_ack_pending":-1,"memory_max_stream_bytes":-1,"storage_max_stream_bytes":-1,"max_bytes_required":false},"domain":"hub","api":{"total":10,"errors":1}}"#,
+    )
+    .unwrap();
+
+    assert_eq!(2048, info.storage);
+    assert_eq!(-1, info.limits.max_streams);
+    assert_eq!(Some("hub"), info.domain.as_deref());
+    assert_eq!(1, info.api.errors);
+
+    let json = serde_json::to_vec(&info).unwrap();
+
+    assert_eq!(info, response::<Info>(&json).unwrap());
+
+    let err = response::<Info>(
+        br#"{"error":{"code":503,"err_code":10039,"description":"jetstream not enabled for account"}}"#,
+    );
+
+    assert!(matches!(err, Err(Error::Api(e)) if e.err_code == ErrorCode::NotEnabledForAccount));
+}
+
+#[test]
+fn test_info_request() {
+    let message = InfoRequest {}.to_message("$JS.API", "_INBOX.1").unwrap();
+
+    assert_eq!("PUB $JS.API.INFO _INBOX.1 2", message.to_string());
+    assert_eq!(Some(&bytes::Bytes::from("{}")), message.body());
+}
@@ -0,0 +1,270 @@
+use std::time::Duration;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use serde::Deserialize;
+
+use super::Error;
+use crate::message::{publish, Message};
+use crate::time::nanos;
+
+const ACK: &[u8] = b"+ACK";
+const NAK: &[u8] = b"-NAK";
+const PROGRESS: &[u8] = b"+WPI";
+const NEXT: &[u8] = b"+NXT";
+const TERM: &[u8] = b"+TERM";
+
+/// Body published to the reply subject of a JetStream delivery.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AckKind {
+    Ack,
+    // redeliver, after `delay` if set
+    Nak(Option<Duration>),
+    // work in progress, resets the ack wait timer
+    Progress,
+    // ack and request the next message(s), raw pull request JSON if set
+    Next(Option<Bytes>),
+    // stop redelivering, the reason shows up in the server advisory
+    Term(Option<String>),
+}
+
+#[derive(Deserialize)]
+struct Delay {
+    // nanoseconds
+    delay: u64,
+}
+
+// +ACK | -NAK [{"delay":<ns>} | <duration>] | +WPI | +NXT [<json>] | +TERM [<reason>]
+
+impl AckKind {
+    pub fn to_bytes(&self) -> Bytes {
+        let mut dst = BytesMut::new();
+
+        match self {
+            Self::Ack => dst.put_slice(ACK),
+            Self::Nak(delay) => {
+                dst.put_slice(NAK);
+
+                if let Some(delay) = delay {
+                    dst.put_slice(format!(" {{\"delay\":{}}}", nanos(*delay)).as_bytes());
+                }
+            }
+            Self::Progress => dst.put_slice(PROGRESS),
+            Self::Next(request) => {
+                dst.put_slice(NEXT);
+
+                if let Some(request) = request {
+                    dst.put_u8(b' ');
+                    dst.put_slice(request);
+                }
+            }
+            Self::Term(reason) => {
+                dst.put_slice(TERM);
+
+                if let Some(reason) = reason {
+                    dst.put_u8(b' ');
+                    dst.put_slice(reason.as_bytes());
+                }
+            }
+        }
+
+        dst.freeze()
+    }
+
+    pub fn parse(body: &[u8]) -> Result<Self, Error> {
+        let invalid = || Error::InvalidAck(Bytes::copy_from_slice(body));
+
+        // the server takes an empty body as +ACK
+        let body = body.trim_ascii();
+
+        if body.is_empty() {
+            return Ok(Self::Ack);
+        }
+
+        let (kind, args) = match body.iter().position(|c| c.is_ascii_whitespace()) {
+            Some(idx) => (&body[..idx], Some(body[idx..].trim_ascii_start())),
+            None => (body, None),
+        };
+
+        Ok(match (kind, args) {
+            (ACK, None) => Self::Ack,
+            (PROGRESS, None) => Self::Progress,
+            (NAK, None) => Self::Nak(None),
+            (NAK, Some(args)) if args.starts_with(b"{") => {
+                let delay: Delay = serde_json::from_slice(args).map_err(|_| invalid())?;
+
+                Self::Nak(Some(Duration::from_nanos(delay.delay)))
+            }
+            (NAK, Some(args)) => Self::Nak(Some(duration(args).ok_or_else(invalid)?)),
+            (NEXT, args) => Self::Next(args.map(Bytes::copy_from_slice)),
+            (TERM, args) => Self::Term(args.map(|r| String::from_utf8_lossy(r).into_owned())),
+            _ => return Err(invalid()),
+        })
+    }
+
+    /// `PUB` of this ack to the reply subject of `message`.
+    pub fn reply(&self, message: &Message) -> Result<publish::Payload, Error> {
+        self.frame(message, None)
+    }
+
+    /// Same as `reply`, the server confirms the ack with an empty message
+    /// on `inbox`.
+    pub fn request(
+        &self,
+        message: &Message,
+        inbox: impl Into<Bytes>,
+    ) -> Result<publish::Payload, Error> {
+        self.frame(message, Some(inbox.into()))
+    }
+
+    fn frame(&self, message: &Message, inbox: Option<Bytes>) -> Result<publish::Payload, Error> {
+        let subject = match message {
+            Message::Message(_) | Message::HMessage(_) => message.reply_to(),
+            _ => None,
+        };
+
+        let body = self.to_bytes();
+
+        Ok(publish::Payload {
+            subject: subject.cloned().ok_or(Error::NoReplySubject)?,
+            reply_to: inbox,
+            payload_size: body.len(),
+            payload: Some(body),
+        })
+    }
+}
+
+// Go duration the server takes as well, `5s`, `1m30s`, `1.5h`
+fn duration(text: &[u8]) -> Option<Duration> {
+    let mut rest = std::str::from_utf8(text).ok()?;
+
+    if rest == "0" {
+        return Some(Duration::ZERO);
+    }
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut nanos = 0f64;
+
+    while !rest.is_empty() {
+        let end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let value: f64 = rest[..end].parse().ok()?;
+
+        rest = &rest[end..];
+
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let unit = match &rest[..end] {
+            "ns" => 1.0,
+            "us" | "\u{b5}s" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return None,
+        };
+
+        nanos += value * unit;
+        rest = &rest[end..];
+    }
+
+    Some(Duration::from_nanos(nanos.min(u64::MAX as f64) as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::{AckKind, Error};
+    use crate::message::{message, Message};
+
+    #[test]
+    fn test_round_trip() {
+        let cases = [
+            (AckKind::Ack, "+ACK"),
+            (AckKind::Nak(None), "-NAK"),
+            (
+                AckKind::Nak(Some(Duration::from_secs(5))),
+                "-NAK {\"delay\":5000000000}",
+            ),
+            (AckKind::Progress, "+WPI"),
+            (AckKind::Next(None), "+NXT"),
+            (
+                AckKind::Next(Some(Bytes::from("{\"batch\":10}"))),
+                "+NXT {\"batch\":10}",
+            ),
+            (AckKind::Term(None), "+TERM"),
+            (
+                AckKind::Term(Some("bad payload".into())),
+                "+TERM bad payload",
+            ),
+        ];
+
+        for (kind, body) in cases {
+            assert_eq!(Bytes::from(body), kind.to_bytes());
+            assert_eq!(kind, AckKind::parse(body.as_bytes()).unwrap());
+        }
+
+        assert_eq!(AckKind::Ack, AckKind::parse(b"").unwrap());
+        assert_eq!(AckKind::Ack, AckKind::parse(b" +ACK\r\n").unwrap());
+
+        for (body, delay) in [
+            ("-NAK 5s", Duration::from_secs(5)),
+            ("-NAK 1m30s", Duration::from_secs(90)),
+            ("-NAK 1.5s", Duration::from_millis(1500)),
+            ("-NAK 250ms", Duration::from_millis(250)),
+            ("-NAK 0", Duration::ZERO),
+        ] {
+            assert_eq!(
+                AckKind::Nak(Some(delay)),
+                AckKind::parse(body.as_bytes()).unwrap()
+            );
+        }
+
+        for body in [
+            &b"+OK"[..],
+            b"+ACK now",
+            b"-NAK {\"delay\":\"5s\"}",
+            b"-NAK 5",
+            b"-NAK 5d",
+            b"-NAK s",
+        ] {
+            assert!(matches!(AckKind::parse(body), Err(Error::InvalidAck(_))));
+        }
+    }
+
+    #[test]
+    fn test_reply() {
+        let delivery = Message::Message(message::Payload {
+            subject: Bytes::from("orders.eu"),
+            sid: 1,
+            reply_to: Some(Bytes::from(
+                "$JS.ACK.ORDERS.processor.1.108.8.1677665731500000000.0",
+            )),
+            payload_size: 5,
+            payload: Some(Bytes::from("hello")),
+        });
+
+        let ack = AckKind::Ack.reply(&delivery).unwrap();
+
+        assert_eq!(
+            "PUB $JS.ACK.ORDERS.processor.1.108.8.1677665731500000000.0 4",
+            Message::Publish(ack).to_string()
+        );
+
+        let ack = AckKind::Term(None).request(&delivery, "_INBOX.1").unwrap();
+
+        assert_eq!(Some(Bytes::from("_INBOX.1")), ack.reply_to);
+        assert_eq!(Some(Bytes::from("+TERM")), ack.payload);
+
+        assert!(matches!(
+            AckKind::Ack.reply(&Message::publish("orders.eu", "hello")),
+            Err(Error::NoReplySubject)
+        ));
+    }
+}
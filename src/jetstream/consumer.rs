@@ -0,0 +1,367 @@
+use serde::{Deserialize, Serialize};
+
+use super::stream::ClusterInfo;
+use super::{name, nullable, Error, Paging, Request, SuccessResponse};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliverPolicy {
+    #[default]
+    All,
+    Last,
+    New,
+    ByStartSequence,
+    ByStartTime,
+    LastPerSubject,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AckPolicy {
+    None,
+    All,
+    #[default]
+    Explicit,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayPolicy {
+    #[default]
+    Instant,
+    Original,
+}
+
+/// Consumer configuration, push based when `deliver_subject` is set.
+/// Durations are in nanoseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub durable_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub deliver_policy: DeliverPolicy,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opt_start_seq: Option<u64>,
+    // RFC 3339 timestamp
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opt_start_time: Option<String>,
+    pub ack_policy: AckPolicy,
+    pub ack_wait: i64,
+    pub max_deliver: i64,
+    #[serde(skip_serializing_if = "Vec::is_empty", deserialize_with = "nullable")]
+    pub backoff: Vec<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter_subject: Option<String>,
+    pub replay_policy: ReplayPolicy,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit_bps: Option<u64>,
+    pub max_ack_pending: i64,
+    // pull consumers only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_waiting: Option<i64>,
+    // push consumers only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deliver_subject: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deliver_group: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_heartbeat: Option<i64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub flow_control: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub headers_only: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inactive_threshold: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_replicas: Option<usize>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub mem_storage: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            durable_name: None,
+            name: None,
+            description: None,
+            deliver_policy: DeliverPolicy::All,
+            opt_start_seq: None,
+            opt_start_time: None,
+            ack_policy: AckPolicy::Explicit,
+            ack_wait: 0,
+            max_deliver: -1,
+            backoff: Vec::new(),
+            filter_subject: None,
+            replay_policy: ReplayPolicy::Instant,
+            rate_limit_bps: None,
+            max_ack_pending: -1,
+            max_waiting: None,
+            deliver_subject: None,
+            deliver_group: None,
+            idle_heartbeat: None,
+            flow_control: false,
+            headers_only: false,
+            inactive_threshold: None,
+            num_replicas: None,
+            mem_storage: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SequenceInfo {
+    pub consumer_seq: u64,
+    pub stream_seq: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_active: Option<String>,
+}
+
+/// Response of consumer CREATE/INFO.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Info {
+    pub stream_name: String,
+    pub name: String,
+    pub created: String,
+    pub config: Config,
+    pub delivered: SequenceInfo,
+    pub ack_floor: SequenceInfo,
+    pub num_ack_pending: u64,
+    pub num_redelivered: u64,
+    pub num_waiting: u64,
+    pub num_pending: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<ClusterInfo>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub push_bound: bool,
+}
+
+/// Creates or updates a consumer, ephemeral when it has no name.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct CreateRequest {
+    pub stream_name: String,
+    pub config: Config,
+}
+
+impl Request for CreateRequest {
+    type Response = Info;
+
+    fn subject(&self) -> Result<String, Error> {
+        let stream = name(&self.stream_name)?;
+        let consumer = self
+            .config
+            .name
+            .as_ref()
+            .or(self.config.durable_name.as_ref())
+            .map(String::as_str)
+            .map(name)
+            .transpose()?;
+
+        Ok(match (consumer, &self.config.filter_subject) {
+            (Some(consumer), Some(filter)) => {
+                format!("CONSUMER.CREATE.{}.{}.{}", stream, consumer, filter)
+            }
+            (Some(consumer), None) => format!("CONSUMER.CREATE.{}.{}", stream, consumer),
+            (None, _) => format!("CONSUMER.CREATE.{}", stream),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct InfoRequest {
+    #[serde(skip)]
+    pub stream: String,
+    #[serde(skip)]
+    pub name: String,
+}
+
+impl Request for InfoRequest {
+    type Response = Info;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!(
+            "CONSUMER.INFO.{}.{}",
+            name(&self.stream)?,
+            name(&self.name)?
+        ))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct DeleteRequest {
+    #[serde(skip)]
+    pub stream: String,
+    #[serde(skip)]
+    pub name: String,
+}
+
+impl Request for DeleteRequest {
+    type Response = SuccessResponse;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!(
+            "CONSUMER.DELETE.{}.{}",
+            name(&self.stream)?,
+            name(&self.name)?
+        ))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ListRequest {
+    #[serde(skip)]
+    pub stream: String,
+    pub offset: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListResponse {
+    #[serde(flatten)]
+    pub paging: Paging,
+    #[serde(default, deserialize_with = "nullable")]
+    pub consumers: Vec<Info>,
+}
+
+impl Request for ListRequest {
+    type Response = ListResponse;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!("CONSUMER.LIST.{}", name(&self.stream)?))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct NamesRequest {
+    #[serde(skip)]
+    pub stream: String,
+    pub offset: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamesResponse {
+    #[serde(flatten)]
+    pub paging: Paging,
+    #[serde(default, deserialize_with = "nullable")]
+    pub consumers: Vec<String>,
+}
+
+impl Request for NamesRequest {
+    type Response = NamesResponse;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!("CONSUMER.NAMES.{}", name(&self.stream)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::response;
+    use super::*;
+
+    const INFO: &str = r#"{
+        "type": "io.nats.jetstream.api.v1.consumer_info_response",
+        "stream_name": "ORDERS",
+        "name": "processor",
+        "created": "2023-03-01T10:20:00.5Z",
+        "config": {
+            "durable_name": "processor",
+            "name": "processor",
+            "deliver_policy": "by_start_sequence",
+            "opt_start_seq": 100,
+            "ack_policy": "explicit",
+            "ack_wait": 30000000000,
+            "max_deliver": 5,
+            "backoff": [1000000000, 5000000000],
+            "filter_subject": "orders.eu",
+            "replay_policy": "instant",
+            "max_ack_pending": 1000,
+            "max_waiting": 512,
+            "num_replicas": 0
+        },
+        "delivered": {"consumer_seq": 8, "stream_seq": 108, "last_active": "2023-03-01T10:21:00Z"},
+        "ack_floor": {"consumer_seq": 6, "stream_seq": 106},
+        "num_ack_pending": 2,
+        "num_redelivered": 1,
+        "num_waiting": 1,
+        "num_pending": 42,
+        "cluster": {"leader": "n1"}
+    }"#;
+
+    #[test]
+    fn test_info() {
+        let info: Info = response(INFO.as_bytes()).unwrap();
+
+        assert_eq!("ORDERS", info.stream_name);
+        assert_eq!(DeliverPolicy::ByStartSequence, info.config.deliver_policy);
+        assert_eq!(Some(100), info.config.opt_start_seq);
+        assert_eq!(vec![1_000_000_000, 5_000_000_000], info.config.backoff);
+        assert_eq!(108, info.delivered.stream_seq);
+        assert_eq!(None, info.ack_floor.last_active);
+        assert_eq!(42, info.num_pending);
+
+        let json = serde_json::to_vec(&info).unwrap();
+
+        assert_eq!(info, response::<Info>(&json).unwrap());
+    }
+
+    #[test]
+    fn test_create_subject() {
+        let mut request = CreateRequest {
+            stream_name: "ORDERS".into(),
+            config: Config::default(),
+        };
+
+        assert_eq!("CONSUMER.CREATE.ORDERS", request.subject().unwrap());
+
+        request.config.durable_name = Some("processor".into());
+
+        assert_eq!(
+            "CONSUMER.CREATE.ORDERS.processor",
+            request.subject().unwrap()
+        );
+
+        request.config.filter_subject = Some("orders.eu".into());
+
+        assert_eq!(
+            "CONSUMER.CREATE.ORDERS.processor.orders.eu",
+            request.subject().unwrap()
+        );
+
+        request.config.durable_name = Some("processor.eu".into());
+
+        assert!(matches!(
+            request.subject(),
+            Err(Error::InvalidName(name)) if name == "processor.eu"
+        ));
+
+        let json = serde_json::to_value(&request).unwrap();
+
+        assert_eq!("ORDERS", json["stream_name"]);
+        assert_eq!("explicit", json["config"]["ack_policy"]);
+        assert_eq!("all", json["config"]["deliver_policy"]);
+        assert!(json["config"].get("deliver_subject").is_none());
+    }
+
+    #[test]
+    fn test_names() {
+        let names: NamesResponse = response(
+            br#"{"type":"io.nats.jetstream.api.v1.consumer_names_response","total":2,"offset":0,"limit":1024,"consumers":["a","b"]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(vec!["a".to_string(), "b".to_string()], names.consumers);
+        assert_eq!(
+            "CONSUMER.NAMES.ORDERS",
+            NamesRequest {
+                stream: "ORDERS".into(),
+                offset: 0
+            }
+            .subject()
+            .unwrap()
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::{error, fmt};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+/// `error` object of a failed `$JS.API` response.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiError {
+    // HTTP like status, 404 for missing streams/consumers
+    pub code: u16,
+    #[serde(default)]
+    pub err_code: ErrorCode,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "jetstream api error {} ({}): {}",
+            self.code,
+            u64::from(self.err_code),
+            self.description
+        )
+    }
+}
+
+impl error::Error for ApiError {}
+
+/// Server `err_code`, codes the crate doesn't know are kept in `Other`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u64", into = "u64")]
+pub enum ErrorCode {
+    BadRequest,
+    ConsumerNameExists,
+    ConsumerNotFound,
+    InsufficientResources,
+    MaximumConsumersLimit,
+    MaximumStreamsLimit,
+    NoMessageFound,
+    NotEnabled,
+    NotEnabledForAccount,
+    StreamNameExists,
+    StreamNotFound,
+    StreamMismatch,
+    StreamSubjectOverlap,
+    StreamWrongLastMsgId,
+    StreamWrongLastSequence,
+    // error without `err_code`, servers before 2.3
+    #[default]
+    Unknown,
+    Other(u64),
+}
+
+impl From<u64> for ErrorCode {
+    fn from(code: u64) -> Self {
+        match code {
+            0 => Self::Unknown,
+            10003 => Self::BadRequest,
+            10013 => Self::ConsumerNameExists,
+            10014 => Self::ConsumerNotFound,
+            10023 => Self::InsufficientResources,
+            10026 => Self::MaximumConsumersLimit,
+            10027 => Self::MaximumStreamsLimit,
+            10037 => Self::NoMessageFound,
+            10039 => Self::NotEnabledForAccount,
+            10058 => Self::StreamNameExists,
+            10059 => Self::StreamNotFound,
+            10060 => Self::StreamMismatch,
+            10065 => Self::StreamSubjectOverlap,
+            10070 => Self::StreamWrongLastMsgId,
+            10071 => Self::StreamWrongLastSequence,
+            10076 => Self::NotEnabled,
+            code => Self::Other(code),
+        }
+    }
+}
+
+impl From<ErrorCode> for u64 {
+    fn from(code: ErrorCode) -> Self {
+        match code {
+            ErrorCode::Unknown => 0,
+            ErrorCode::BadRequest => 10003,
+            ErrorCode::ConsumerNameExists => 10013,
+            ErrorCode::ConsumerNotFound => 10014,
+            ErrorCode::InsufficientResources => 10023,
+            ErrorCode::MaximumConsumersLimit => 10026,
+            ErrorCode::MaximumStreamsLimit => 10027,
+            ErrorCode::NoMessageFound => 10037,
+            ErrorCode::NotEnabledForAccount => 10039,
+            ErrorCode::StreamNameExists => 10058,
+            ErrorCode::StreamNotFound => 10059,
+            ErrorCode::StreamMismatch => 10060,
+            ErrorCode::StreamSubjectOverlap => 10065,
+            ErrorCode::StreamWrongLastMsgId => 10070,
+            ErrorCode::StreamWrongLastSequence => 10071,
+            ErrorCode::NotEnabled => 10076,
+            ErrorCode::Other(code) => code,
+        }
+    }
+}
+
+/// Failure decoding a `$JS.API` response or a JetStream subject.
+#[derive(Debug)]
+pub enum Error {
+    Api(ApiError),
+    Json(serde_json::Error),
+    // reply subject that isn't a `$JS.ACK` subject
+    InvalidAckSubject(Bytes),
+    // body that isn't one of the ack payloads
+    InvalidAck(Bytes),
+    // only MSG/HMSG with a reply subject can be acked
+    NoReplySubject,
+    // stream or consumer name that isn't a single subject token
+    InvalidName(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Api(e) => e.fmt(f),
+            Self::Json(e) => write!(f, "invalid jetstream api response: {}", e),
+            Self::InvalidAckSubject(s) => {
+                write!(f, "invalid ack subject '{}'", String::from_utf8_lossy(s))
+            }
+            Self::InvalidAck(s) => write!(f, "invalid ack '{}'", String::from_utf8_lossy(s)),
+            Self::NoReplySubject => write!(f, "message has no reply subject"),
+            Self::InvalidName(name) => write!(f, "invalid stream or consumer name '{}'", name),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Api(e) => Some(e),
+            Self::Json(e) => Some(e),
+            Self::InvalidAckSubject(_)
+            | Self::InvalidAck(_)
+            | Self::NoReplySubject
+            | Self::InvalidName(_) => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Json(e)
+    }
+}
+
+#[test]
+fn test_error_code() {
+    for code in [0, 10003, 10014, 10059, 10071, 10076, 99999] {
+        assert_eq!(code, u64::from(ErrorCode::from(code)));
+    }
+
+    assert_eq!(ErrorCode::Other(99999), ErrorCode::from(99999));
+}
@@ -0,0 +1,664 @@
+use std::time::Duration;
+use std::{error, fmt};
+
+use bytes::Bytes;
+
+use super::consumer::{self, AckPolicy, DeliverPolicy};
+use super::publish::PublishOptions;
+use super::pull::Status;
+use super::stream::{self, DiscardPolicy, StoredMessage};
+use super::JsMetadata;
+use crate::message::{Headers, Message};
+use crate::name::is_name;
+use crate::ProtocolError;
+
+pub const OPERATION: &str = "KV-Operation";
+pub const ROLLUP: &str = "Nats-Rollup";
+
+/// Key-Value bucket, a stream named `KV_<bucket>` on `$KV.<bucket>.>`.
+///
+/// Values are read with `get` requests or a [`Watcher`] over the
+/// deliveries of a `watch` consumer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bucket {
+    name: String,
+    // `$KV.<bucket>.`
+    prefix: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    #[default]
+    Put,
+    Delete,
+    Purge,
+}
+
+/// Value of a key at `revision`, the stream sequence of its message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub bucket: String,
+    pub key: String,
+    pub value: Bytes,
+    pub revision: u64,
+    // entries still pending for the watcher, 0 once caught up
+    pub delta: u64,
+    // nanoseconds since the unix epoch, only known for deliveries
+    pub timestamp: Option<u64>,
+    pub operation: Operation,
+}
+
+impl Bucket {
+    pub fn new(name: impl Into<String>) -> Result<Self, KvError> {
+        let name = name.into();
+
+        if !is_name(&name) {
+            return Err(KvError::InvalidBucket(name));
+        }
+
+        Ok(Self {
+            prefix: format!("$KV.{}.", name),
+            name,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn stream_name(&self) -> String {
+        format!("KV_{}", self.name)
+    }
+
+    /// `$KV.<bucket>.<key>`
+    pub fn subject(&self, key: &str) -> Result<String, KvError> {
+        validate_key(key, false)?;
+
+        Ok(format!("{}{}", self.prefix, key))
+    }
+
+    /// Stream configuration of the bucket, keeping `history` values per key.
+    pub fn config(&self, history: i64, ttl: Option<Duration>) -> stream::Config {
+        stream::Config {
+            name: self.stream_name(),
+            subjects: vec![format!("{}>", self.prefix)],
+            max_msgs_per_subject: history,
+            max_age: ttl.map_or(0, |ttl| ttl.as_nanos().min(i64::MAX as u128) as i64),
+            discard: DiscardPolicy::New,
+            allow_rollup_hdrs: true,
+            deny_delete: true,
+            allow_direct: true,
+            ..Default::default()
+        }
+    }
+
+    /// Publish of a new value, acknowledged with a `PubAck` on `inbox`.
+    pub fn put(
+        &self,
+        key: &str,
+        value: impl Into<Bytes>,
+        inbox: impl Into<Bytes>,
+    ) -> Result<Message, KvError> {
+        Ok(Message::publish_with_reply(
+            self.subject(key)?,
+            inbox,
+            value,
+        ))
+    }
+
+    /// Put failing with `PublishError::WrongLastSequence` when the key exists.
+    pub fn create(
+        &self,
+        key: &str,
+        value: impl Into<Bytes>,
+        inbox: impl Into<Bytes>,
+    ) -> Result<Message, KvError> {
+        self.update(key, value, 0, inbox)
+    }
+
+    /// Put failing unless the key is still at `revision`.
+    pub fn update(
+        &self,
+        key: &str,
+        value: impl Into<Bytes>,
+        revision: u64,
+        inbox: impl Into<Bytes>,
+    ) -> Result<Message, KvError> {
+        let options = PublishOptions::new().with_expected_last_subject_sequence(revision);
+
+        Ok(options.to_message(self.subject(key)?, inbox, value)?)
+    }
+
+    /// Delete marker, history is kept.
+    pub fn delete(&self, key: &str, inbox: impl Into<Bytes>) -> Result<Message, KvError> {
+        let mut headers = Headers::new();
+        headers.insert(OPERATION, "DEL");
+
+        Ok(Message::hpublish_with_reply(
+            self.subject(key)?,
+            inbox,
+            &headers,
+            Bytes::new(),
+        )?)
+    }
+
+    /// Purge marker, rolls up the history of the key.
+    pub fn purge(&self, key: &str, inbox: impl Into<Bytes>) -> Result<Message, KvError> {
+        let mut headers = Headers::new();
+        headers.insert(OPERATION, "PURGE").insert(ROLLUP, "sub");
+
+        Ok(Message::hpublish_with_reply(
+            self.subject(key)?,
+            inbox,
+            &headers,
+            Bytes::new(),
+        )?)
+    }
+
+    /// Latest value of `key`, see [`Bucket::entry_from_stored`].
+    pub fn get(&self, key: &str) -> Result<stream::MsgGetRequest, KvError> {
+        Ok(stream::MsgGetRequest {
+            stream: self.stream_name(),
+            last_by_subj: Some(self.subject(key)?),
+            ..Default::default()
+        })
+    }
+
+    /// Entry at `revision`, whichever key it belongs to.
+    pub fn get_revision(&self, revision: u64) -> stream::MsgGetRequest {
+        stream::MsgGetRequest {
+            stream: self.stream_name(),
+            seq: Some(revision),
+            ..Default::default()
+        }
+    }
+
+    /// Ephemeral push consumer delivering the keys matching `pattern` to
+    /// `deliver_subject`: the latest value of each key, then every update.
+    /// With `history` every stored value is delivered first.
+    pub fn watch(
+        &self,
+        pattern: &str,
+        deliver_subject: impl Into<String>,
+        history: bool,
+    ) -> Result<consumer::CreateRequest, KvError> {
+        let deliver_policy = match history {
+            true => DeliverPolicy::All,
+            false => DeliverPolicy::LastPerSubject,
+        };
+
+        self.watch_with(pattern, deliver_subject.into(), deliver_policy, None)
+    }
+
+    /// Resumes a watch after `revision`, see [`WatchEvent::Gap`].
+    pub fn watch_from(
+        &self,
+        pattern: &str,
+        deliver_subject: impl Into<String>,
+        revision: u64,
+    ) -> Result<consumer::CreateRequest, KvError> {
+        self.watch_with(
+            pattern,
+            deliver_subject.into(),
+            DeliverPolicy::ByStartSequence,
+            Some(revision.saturating_add(1)),
+        )
+    }
+
+    fn watch_with(
+        &self,
+        pattern: &str,
+        deliver_subject: String,
+        deliver_policy: DeliverPolicy,
+        opt_start_seq: Option<u64>,
+    ) -> Result<consumer::CreateRequest, KvError> {
+        validate_key(pattern, true)?;
+
+        Ok(consumer::CreateRequest {
+            stream_name: self.stream_name(),
+            config: consumer::Config {
+                deliver_subject: Some(deliver_subject),
+                deliver_policy,
+                opt_start_seq,
+                ack_policy: AckPolicy::None,
+                max_deliver: 1,
+                filter_subject: Some(format!("{}{}", self.prefix, pattern)),
+                flow_control: true,
+                idle_heartbeat: Some(5_000_000_000),
+                mem_storage: true,
+                num_replicas: Some(1),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Entry of a watch delivery.
+    pub fn entry(&self, message: &Message) -> Result<Entry, KvError> {
+        let key = message
+            .subject()
+            .and_then(|s| std::str::from_utf8(s).ok())
+            .and_then(|s| s.strip_prefix(self.prefix.as_str()))
+            .ok_or(KvError::InvalidEntry)?;
+
+        let metadata = JsMetadata::from_message(message)
+            .ok_or(KvError::InvalidEntry)?
+            .map_err(|_| KvError::InvalidEntry)?;
+
+        let headers = message.headers().map_err(|_| KvError::InvalidEntry)?;
+
+        Ok(Entry {
+            bucket: self.name.clone(),
+            key: key.to_string(),
+            value: message.body().cloned().unwrap_or_default(),
+            revision: metadata.stream_sequence,
+            delta: metadata.pending,
+            timestamp: Some(metadata.timestamp),
+            operation: operation(&headers)?,
+        })
+    }
+
+    /// Entry of a `get` response.
+    pub fn entry_from_stored(&self, stored: &StoredMessage) -> Result<Entry, KvError> {
+        let key = stored
+            .subject
+            .strip_prefix(self.prefix.as_str())
+            .ok_or(KvError::InvalidEntry)?;
+
+        let headers = stored.headers().map_err(|_| KvError::InvalidEntry)?;
+
+        Ok(Entry {
+            bucket: self.name.clone(),
+            key: key.to_string(),
+            value: stored.data.clone(),
+            revision: stored.seq,
+            delta: 0,
+            timestamp: None,
+            operation: operation(&headers)?,
+        })
+    }
+}
+
+fn operation(headers: &Headers) -> Result<Operation, KvError> {
+    Ok(match headers.get(OPERATION) {
+        None | Some("PUT") => Operation::Put,
+        Some("DEL") => Operation::Delete,
+        Some("PURGE") => Operation::Purge,
+        Some(_) => return Err(KvError::InvalidEntry),
+    })
+}
+
+/// `[-/_=.a-zA-Z0-9]+` split by dots into non-empty tokens, `*` and `>`
+/// tokens are allowed in watch patterns.
+pub fn validate_key(key: &str, pattern: bool) -> Result<(), KvError> {
+    let invalid = || KvError::InvalidKey(key.to_string());
+
+    if key.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut tokens = key.split('.').peekable();
+
+    while let Some(token) = tokens.next() {
+        let valid = match token {
+            "" => false,
+            "*" => pattern,
+            // full wildcard only as the last token
+            ">" => pattern && tokens.peek().is_none(),
+            token => token
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, b'-' | b'/' | b'_' | b'=')),
+        };
+
+        if !valid {
+            return Err(invalid());
+        }
+    }
+
+    Ok(())
+}
+
+/// Watch consumer deliveries turned into entries, in revision order.
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    bucket: Bucket,
+    // last consumer sequence, the ordered consumer delivers without gaps
+    consumer_sequence: u64,
+    revision: u64,
+    caught_up: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    Entry(Entry),
+    // every value stored when the watch started was delivered, sent once.
+    // For an empty bucket it comes from `created`, or with the first heartbeat
+    CaughtUp,
+    // a delivery was missed, recreate the consumer with `watch_from(revision)`
+    Gap { revision: u64 },
+    // the server waits for an empty message on `reply_to`
+    FlowControl { reply_to: Bytes },
+    Heartbeat,
+}
+
+impl Watcher {
+    pub fn new(bucket: Bucket) -> Self {
+        Self {
+            bucket,
+            consumer_sequence: 0,
+            revision: 0,
+            caught_up: false,
+        }
+    }
+
+    /// `CaughtUp` right away when the watch consumer was created with
+    /// nothing pending.
+    pub fn created(&mut self, info: &consumer::Info) -> Vec<WatchEvent> {
+        if self.caught_up || self.consumer_sequence != 0 || info.num_pending != 0 {
+            return vec![];
+        }
+
+        self.caught_up = true;
+
+        vec![WatchEvent::CaughtUp]
+    }
+
+    /// Last revision delivered.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Events for one delivery, an entry may be followed by `CaughtUp`.
+    pub fn process(&mut self, message: &Message) -> Result<Vec<WatchEvent>, KvError> {
+        match Status::from_message(message).map_err(|_| KvError::InvalidEntry)? {
+            Some(Status::FlowControl) => {
+                let reply_to = message.reply_to().cloned().unwrap_or_default();
+
+                return Ok(vec![WatchEvent::FlowControl { reply_to }]);
+            }
+            Some(Status::IdleHeartbeat) => {
+                let mut events = vec![WatchEvent::Heartbeat];
+
+                // no deliveries at all, the bucket is empty
+                if !self.caught_up && self.consumer_sequence == 0 {
+                    self.caught_up = true;
+                    events.push(WatchEvent::CaughtUp);
+                }
+
+                return Ok(events);
+            }
+            // `409 Consumer Deleted` and the like end the watch
+            Some(status) => return Err(KvError::Status(status)),
+            None => {}
+        }
+
+        let metadata = JsMetadata::from_message(message)
+            .ok_or(KvError::InvalidEntry)?
+            .map_err(|_| KvError::InvalidEntry)?;
+
+        if metadata.consumer_sequence != self.consumer_sequence + 1 {
+            return Ok(vec![WatchEvent::Gap {
+                revision: self.revision,
+            }]);
+        }
+
+        let entry = self.bucket.entry(message)?;
+
+        self.consumer_sequence = metadata.consumer_sequence;
+        self.revision = entry.revision;
+
+        let mut events = vec![WatchEvent::Entry(entry)];
+
+        if !self.caught_up && metadata.pending == 0 {
+            self.caught_up = true;
+            events.push(WatchEvent::CaughtUp);
+        }
+
+        Ok(events)
+    }
+
+    /// Start over after the consumer was recreated following a gap.
+    pub fn reset(&mut self) {
+        self.consumer_sequence = 0;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvError {
+    InvalidBucket(String),
+    InvalidKey(String),
+    // message that isn't a delivery of the bucket
+    InvalidEntry,
+    // status other than flow control or a heartbeat sent to a watcher
+    Status(Status),
+    Protocol(ProtocolError),
+}
+
+impl fmt::Display for KvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidBucket(name) => write!(f, "invalid bucket name '{}'", name),
+            Self::InvalidKey(key) => write!(f, "invalid key '{}'", key),
+            Self::InvalidEntry => write!(f, "invalid key-value entry"),
+            Self::Status(status) => write!(f, "watch ended by status {:?}", status),
+            Self::Protocol(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for KvError {}
+
+impl From<ProtocolError> for KvError {
+    fn from(e: ProtocolError) -> Self {
+        Self::Protocol(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+    use tokio_util::codec::Encoder;
+
+    use super::*;
+    use crate::test_support::delivery;
+    use crate::Codec;
+
+    #[test]
+    fn test_keys() {
+        assert!(Bucket::new("config").is_ok());
+        assert_eq!(
+            Err(KvError::InvalidBucket("con.fig".into())),
+            Bucket::new("con.fig")
+        );
+
+        for key in ["a", "app.db/url", "a-b_c=d", "A.1"] {
+            assert_eq!(Ok(()), validate_key(key, false));
+        }
+        for key in ["", ".a", "a.", "a..b", "a b", "a.*", "a.>", "ä"] {
+            assert!(validate_key(key, false).is_err(), "{}", key);
+        }
+
+        assert_eq!(Ok(()), validate_key("app.*.url", true));
+        assert_eq!(Ok(()), validate_key(">", true));
+        assert!(validate_key("a.>.b", true).is_err());
+    }
+
+    #[test]
+    fn test_requests() {
+        let bucket = Bucket::new("config").unwrap();
+        let mut codec = Codec::new();
+        let mut dst = BytesMut::new();
+
+        for message in [
+            bucket.put("app.url", "http://a", "_INBOX.1").unwrap(),
+            bucket.create("app.url", "http://a", "_INBOX.2").unwrap(),
+            bucket.delete("app.url", "_INBOX.3").unwrap(),
+        ] {
+            codec.encode(message, &mut dst).unwrap();
+        }
+
+        assert_eq!(
+            &b"PUB $KV.config.app.url _INBOX.1 8\r\nhttp://a\r\n\
+               HPUB $KV.config.app.url _INBOX.2 52 60\r\nNATS/1.0\r\nNats-Expected-Last-Subject-Sequence: 0\r\n\r\nhttp://a\r\n\
+               HPUB $KV.config.app.url _INBOX.3 31 31\r\nNATS/1.0\r\nKV-Operation: DEL\r\n\r\n\r\n"[..],
+            &dst[..]
+        );
+
+        let purge = bucket.purge("app.url", "_INBOX.4").unwrap();
+        let headers = purge.headers().unwrap();
+
+        assert_eq!(Some("PURGE"), headers.get(OPERATION));
+        assert_eq!(Some("sub"), headers.get(ROLLUP));
+
+        assert!(bucket.put("app.*", "", "_INBOX.1").is_err());
+
+        let get = bucket.get("app.url").unwrap();
+
+        assert_eq!("KV_config", get.stream);
+        assert_eq!(Some("$KV.config.app.url"), get.last_by_subj.as_deref());
+
+        let config = bucket.config(5, None);
+
+        assert_eq!(vec!["$KV.config.>".to_string()], config.subjects);
+        assert_eq!(5, config.max_msgs_per_subject);
+        assert!(config.allow_rollup_hdrs);
+
+        let watch = bucket.watch("app.>", "_INBOX.w", false).unwrap();
+
+        assert_eq!(
+            Some("$KV.config.app.>"),
+            watch.config.filter_subject.as_deref()
+        );
+        assert_eq!(DeliverPolicy::LastPerSubject, watch.config.deliver_policy);
+
+        let watch = bucket.watch_from("app.>", "_INBOX.w", 41).unwrap();
+
+        assert_eq!(Some(42), watch.config.opt_start_seq);
+
+        let watch = bucket.watch_from("app.>", "_INBOX.w", u64::MAX).unwrap();
+
+        assert_eq!(Some(u64::MAX), watch.config.opt_start_seq);
+    }
+
+    #[test]
+    fn test_entries() {
+        let bucket = Bucket::new("config").unwrap();
+
+        let entry = bucket
+            .entry(&delivery(
+                "MSG $KV.config.app.url 1 $JS.ACK.KV_config.w.1.12.1.1677665731500000000.1 8\r\nhttp://a\r\n",
+            ))
+            .unwrap();
+
+        assert_eq!("app.url", entry.key);
+        assert_eq!(Bytes::from("http://a"), entry.value);
+        assert_eq!(12, entry.revision);
+        assert_eq!(1, entry.delta);
+        assert_eq!(Operation::Put, entry.operation);
+
+        let entry = bucket
+            .entry(&delivery(
+                "HMSG $KV.config.app.url 1 $JS.ACK.KV_config.w.1.13.2.1677665731500000000.0 31 31\r\nNATS/1.0\r\nKV-Operation: DEL\r\n\r\n\r\n",
+            ))
+            .unwrap();
+
+        assert_eq!(Operation::Delete, entry.operation);
+        assert!(entry.value.is_empty());
+
+        assert_eq!(
+            Err(KvError::InvalidEntry),
+            bucket.entry(&delivery(
+                "MSG $KV.other.app.url 1 $JS.ACK.KV_other.w.1.12.1.1677665731500000000.1 0\r\n\r\n"
+            ))
+        );
+
+        let stored = StoredMessage {
+            subject: "$KV.config.app.url".into(),
+            seq: 13,
+            hdrs: Some(Bytes::from("NATS/1.0\r\nKV-Operation: PURGE\r\n\r\n")),
+            data: Bytes::new(),
+            time: "2023-03-01T10:15:31.5Z".into(),
+        };
+
+        let entry = bucket.entry_from_stored(&stored).unwrap();
+
+        assert_eq!(13, entry.revision);
+        assert_eq!(Operation::Purge, entry.operation);
+    }
+
+    #[test]
+    fn test_watcher() {
+        let mut watcher = Watcher::new(Bucket::new("config").unwrap());
+
+        let events = watcher
+            .process(&delivery(
+                "MSG $KV.config.a 1 $JS.ACK.KV_config.w.1.5.1.1677665731500000000.1 1\r\n1\r\n",
+            ))
+            .unwrap();
+
+        assert!(matches!(&events[..], [WatchEvent::Entry(e)] if e.key == "a"));
+
+        let events = watcher
+            .process(&delivery(
+                "MSG $KV.config.b 1 $JS.ACK.KV_config.w.1.9.2.1677665731500000000.0 1\r\n2\r\n",
+            ))
+            .unwrap();
+
+        assert!(matches!(
+            &events[..],
+            [WatchEvent::Entry(e), WatchEvent::CaughtUp] if e.revision == 9
+        ));
+        assert_eq!(9, watcher.revision());
+
+        let events = watcher
+            .process(&delivery(
+                "HMSG _INBOX.w 1 $JS.FC.KV_config.w.1 36 36\r\nNATS/1.0 100 FlowControl Request\r\n\r\n\r\n",
+            ))
+            .unwrap();
+
+        assert_eq!(
+            vec![WatchEvent::FlowControl {
+                reply_to: Bytes::from("$JS.FC.KV_config.w.1")
+            }],
+            events
+        );
+
+        // consumer sequence 4 after 2, a delivery was lost
+        let events = watcher
+            .process(&delivery(
+                "MSG $KV.config.c 1 $JS.ACK.KV_config.w.1.12.4.1677665731500000000.0 1\r\n3\r\n",
+            ))
+            .unwrap();
+
+        assert_eq!(vec![WatchEvent::Gap { revision: 9 }], events);
+
+        let mut watcher = Watcher::new(Bucket::new("empty").unwrap());
+
+        let events = watcher
+            .process(&delivery(
+                "HMSG _INBOX.w 1 31 31\r\nNATS/1.0 100 Idle Heartbeat\r\n\r\n\r\n",
+            ))
+            .unwrap();
+
+        assert_eq!(vec![WatchEvent::Heartbeat, WatchEvent::CaughtUp], events);
+
+        let mut info: consumer::Info = serde_json::from_str(
+            r#"{"stream_name":"KV_empty","name":"w","created":"2023-03-01T10:20:00.5Z","config":{},"delivered":{},"ack_floor":{},"num_ack_pending":0,"num_redelivered":0,"num_waiting":0,"num_pending":3}"#,
+        )
+        .unwrap();
+        let mut watcher = Watcher::new(Bucket::new("empty").unwrap());
+
+        assert!(watcher.created(&info).is_empty());
+
+        info.num_pending = 0;
+
+        assert_eq!(vec![WatchEvent::CaughtUp], watcher.created(&info));
+        assert!(watcher.created(&info).is_empty());
+
+        assert_eq!(
+            Err(KvError::Status(Status::ConsumerDeleted)),
+            watcher.process(&delivery(
+                "HMSG _INBOX.w 1 33 33\r\nNATS/1.0 409 Consumer Deleted\r\n\r\n\r\n",
+            ))
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+
+use super::Error;
+use crate::message::Message;
+
+const ACK_PREFIX: &str = "$JS.ACK.";
+
+/// Delivery metadata of a JetStream message, carried in its reply subject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsMetadata {
+    // `None` for the legacy layout and for the `_` placeholder
+    pub domain: Option<String>,
+    pub account_hash: Option<String>,
+    pub stream: String,
+    pub consumer: String,
+    // delivery count, 1 for the first delivery
+    pub delivered: u64,
+    pub stream_sequence: u64,
+    pub consumer_sequence: u64,
+    // nanoseconds since the unix epoch
+    pub timestamp: u64,
+    pub pending: u64,
+}
+
+// legacy: $JS.ACK.<stream>.<consumer>.<delivered>.<sseq>.<cseq>.<ts>.<pending>
+// new:    $JS.ACK.<domain>.<hash>.<stream>.<consumer>.<delivered>.<sseq>.<cseq>.<ts>.<pending>[.<token>]*
+
+impl JsMetadata {
+    pub fn parse(reply_to: &[u8]) -> Result<Self, Error> {
+        let invalid = || Error::InvalidAckSubject(Bytes::copy_from_slice(reply_to));
+
+        let subject = std::str::from_utf8(reply_to).map_err(|_| invalid())?;
+        let tokens: Vec<&str> = subject
+            .strip_prefix(ACK_PREFIX)
+            .ok_or_else(invalid)?
+            .split('.')
+            .collect();
+
+        let (domain, account_hash, tokens) = match tokens.len() {
+            7 => (None, None, &tokens[..]),
+            n if n >= 9 => {
+                let domain = Some(tokens[0]).filter(|d| *d != "_");
+
+                (domain, Some(tokens[1]), &tokens[2..9])
+            }
+            _ => return Err(invalid()),
+        };
+
+        if tokens.iter().any(|t| t.is_empty()) {
+            return Err(invalid());
+        }
+
+        let number = |idx: usize| tokens[idx].parse::<u64>().map_err(|_| invalid());
+
+        Ok(Self {
+            domain: domain.map(ToString::to_string),
+            account_hash: account_hash.map(ToString::to_string),
+            stream: tokens[0].to_string(),
+            consumer: tokens[1].to_string(),
+            delivered: number(2)?,
+            stream_sequence: number(3)?,
+            consumer_sequence: number(4)?,
+            timestamp: number(5)?,
+            pending: number(6)?,
+        })
+    }
+
+    /// Metadata of a JetStream delivery, `None` without a reply subject.
+    pub fn from_message(message: &Message) -> Option<Result<Self, Error>> {
+        message.reply_to().map(|reply_to| Self::parse(reply_to))
+    }
+
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_nanos(self.timestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, JsMetadata};
+    use crate::message::Message;
+
+    #[test]
+    fn test_legacy() {
+        let metadata =
+            JsMetadata::parse(b"$JS.ACK.ORDERS.processor.2.108.8.1677665731500000000.42").unwrap();
+
+        assert_eq!(
+            JsMetadata {
+                domain: None,
+                account_hash: None,
+                stream: "ORDERS".into(),
+                consumer: "processor".into(),
+                delivered: 2,
+                stream_sequence: 108,
+                consumer_sequence: 8,
+                timestamp: 1_677_665_731_500_000_000,
+                pending: 42,
+            },
+            metadata
+        );
+        assert_eq!(
+            1_677_665_731,
+            metadata
+                .time()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+        );
+    }
+
+    #[test]
+    fn test_new() {
+        let metadata = JsMetadata::parse(
+            b"$JS.ACK.hub.ACCHASH.ORDERS.processor.1.108.8.1677665731500000000.0.3hzcXPjG",
+        )
+        .unwrap();
+
+        assert_eq!(Some("hub"), metadata.domain.as_deref());
+        assert_eq!(Some("ACCHASH"), metadata.account_hash.as_deref());
+        assert_eq!("ORDERS", metadata.stream);
+        assert_eq!(1, metadata.delivered);
+        assert_eq!(0, metadata.pending);
+
+        let metadata =
+            JsMetadata::parse(b"$JS.ACK._.ACCHASH.ORDERS.processor.1.108.8.1677665731500000000.0")
+                .unwrap();
+
+        assert_eq!(None, metadata.domain);
+        assert_eq!(8, metadata.consumer_sequence);
+    }
+
+    #[test]
+    fn test_invalid() {
+        for reply_to in [
+            &b"_INBOX.abc"[..],
+            b"$JS.ACK.ORDERS.processor.2.108.8",
+            b"$JS.ACK.ORDERS.processor.x.108.8.1677665731500000000.42",
+            b"$JS.ACK.ORDERS..2.108.8.1677665731500000000.42",
+            b"$JS.ACK.hub.HASH.ORDERS.processor.1.108",
+        ] {
+            assert!(matches!(
+                JsMetadata::parse(reply_to),
+                Err(Error::InvalidAckSubject(_))
+            ));
+        }
+
+        assert!(JsMetadata::from_message(&Message::publish("FOO", "")).is_none());
+
+        let message = Message::publish_with_reply(
+            "orders.eu",
+            "$JS.ACK.ORDERS.processor.1.1.1.1677665731500000000.0",
+            "",
+        );
+
+        assert_eq!(
+            "processor",
+            JsMetadata::from_message(&message)
+                .unwrap()
+                .unwrap()
+                .consumer
+        );
+    }
+}
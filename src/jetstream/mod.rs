@@ -0,0 +1,177 @@
+// Types for the JetStream JSON API, carried in PUB/MSG bodies on
+// `$JS.API.>` subjects.
+
+use bytes::Bytes;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::message::Message;
+
+pub mod account;
+pub mod ack;
+pub mod consumer;
+pub mod error;
+pub mod kv;
+pub mod metadata;
+pub mod object;
+pub mod publish;
+pub mod pull;
+pub mod stream;
+
+pub use ack::AckKind;
+pub use error::{ApiError, Error, ErrorCode};
+pub use metadata::JsMetadata;
+pub use publish::{PubAck, PublishOptions};
+
+/// Default API prefix, `$JS.<domain>.API` for a JetStream domain.
+pub const API_PREFIX: &str = "$JS.API";
+
+/// A `$JS.API` request, path parameters are left out of the JSON body.
+pub trait Request: Serialize {
+    type Response: DeserializeOwned;
+
+    /// Subject below the API prefix, `STREAM.INFO.ORDERS`, fails for a
+    /// stream or consumer name that isn't a single subject token.
+    fn subject(&self) -> Result<String, Error>;
+
+    /// `PUB <prefix>.<subject> <reply_to>` carrying the request.
+    fn to_message(&self, prefix: &str, reply_to: impl Into<Bytes>) -> Result<Message, Error> {
+        let subject = format!("{}.{}", prefix, self.subject()?);
+        let body = serde_json::to_vec(self)?;
+
+        Ok(Message::publish_with_reply(subject, reply_to, body))
+    }
+}
+
+/// Decodes a response body, `{"error": ..}` included.
+pub fn response<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Response<T> {
+        Err { error: ApiError },
+        Ok(T),
+    }
+
+    match serde_json::from_slice(body)? {
+        Response::Ok(response) => Ok(response),
+        Response::Err { error } => Err(Error::Api(error)),
+    }
+}
+
+// stream and consumer names are a single subject token
+fn name(name: &str) -> Result<&str, Error> {
+    let valid = !name.is_empty()
+        && !name.bytes().any(|c| {
+            c.is_ascii_whitespace()
+                || c.is_ascii_control()
+                || matches!(c, b'.' | b'*' | b'>' | b'/' | b'\\')
+        });
+
+    match valid {
+        true => Ok(name),
+        false => Err(Error::InvalidName(name.to_string())),
+    }
+}
+
+/// Paging for the LIST/NAMES requests.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Paging {
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Response of the DELETE requests.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SuccessResponse {
+    pub success: bool,
+}
+
+// `null` for empty lists in NAMES responses
+fn nullable<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+// base64 payloads of stored messages
+mod base64 {
+    use ::base64::{engine::general_purpose::STANDARD, Engine};
+    use bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        let text = Option::<String>::deserialize(deserializer)?.unwrap_or_default();
+
+        STANDARD
+            .decode(text)
+            .map(Bytes::from)
+            .map_err(serde::de::Error::custom)
+    }
+
+    pub mod option {
+        use bytes::Bytes;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            data: &Option<Bytes>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match data {
+                Some(data) => super::serialize(data, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Bytes>, D::Error> {
+            #[derive(Deserialize)]
+            struct Data(#[serde(with = "super")] Bytes);
+
+            Ok(Option::<Data>::deserialize(deserializer)?.map(|Data(data)| data))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{response, stream, Error, ErrorCode, Request};
+
+    #[test]
+    fn test_error_response() {
+        let body = br#"{"type":"io.nats.jetstream.api.v1.stream_info_response","error":{"code":404,"err_code":10059,"description":"stream not found"}}"#;
+
+        match response::<stream::Info>(body) {
+            Err(Error::Api(e)) => {
+                assert_eq!(404, e.code);
+                assert_eq!(ErrorCode::StreamNotFound, e.err_code);
+                assert_eq!("stream not found", e.description);
+            }
+            r => panic!("unexpected {:?}", r),
+        }
+
+        assert!(matches!(
+            response::<stream::Info>(b"{\"config\":"),
+            Err(Error::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_to_message() {
+        let request = stream::InfoRequest::new("ORDERS");
+        let message = request.to_message("$JS.API", "_INBOX.1").unwrap();
+
+        assert_eq!(
+            "PUB $JS.API.STREAM.INFO.ORDERS _INBOX.1 2",
+            message.to_string()
+        );
+        assert_eq!(Some(&bytes::Bytes::from("{}")), message.body());
+    }
+}
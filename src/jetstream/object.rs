@@ -0,0 +1,686 @@
+use std::time::SystemTime;
+use std::{error, fmt};
+
+use ::base64::{engine::general_purpose::URL_SAFE, Engine};
+use bytes::{Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::consumer::{self, AckPolicy, DeliverPolicy};
+use super::kv::ROLLUP;
+use super::stream::{self, DiscardPolicy, StoredMessage};
+use crate::message::{Headers, Message};
+use crate::name::is_name;
+use crate::time::rfc3339;
+use crate::ProtocolError;
+
+pub const DEFAULT_CHUNK_SIZE: usize = 128 * 1024;
+
+const DIGEST_PREFIX: &str = "SHA-256=";
+
+/// Object Store bucket, a stream named `OBJ_<bucket>` holding the chunks
+/// on `$O.<bucket>.C.<nuid>` and the meta of every object on
+/// `$O.<bucket>.M.<name>`.
+///
+/// Puts are split into chunk frames here, gets are read back through an
+/// [`ObjectReader`] fed with the consumer deliveries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Store {
+    name: String,
+}
+
+/// Meta JSON of an object, the latest message on its meta subject.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<ObjectOptions>,
+    pub bucket: String,
+    // chunk subject token, new on every put
+    pub nuid: String,
+    pub size: u64,
+    // RFC 3339
+    pub mtime: String,
+    pub chunks: u64,
+    // `SHA-256=<base64url>`, unset for links and deleted objects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<ObjectLink>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_chunk_size: Option<usize>,
+}
+
+/// Object or whole bucket, without `name`, an object points to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ObjectLink {
+    pub bucket: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Store {
+    pub fn new(name: impl Into<String>) -> Result<Self, ObjectError> {
+        let name = name.into();
+
+        if !is_name(&name) {
+            return Err(ObjectError::InvalidBucket(name));
+        }
+
+        Ok(Self { name })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn stream_name(&self) -> String {
+        format!("OBJ_{}", self.name)
+    }
+
+    pub fn chunk_subject(&self, nuid: &str) -> String {
+        format!("$O.{}.C.{}", self.name, nuid)
+    }
+
+    /// The name is base64url encoded, object names are free form.
+    pub fn meta_subject(&self, name: &str) -> String {
+        format!("$O.{}.M.{}", self.name, URL_SAFE.encode(name))
+    }
+
+    pub fn config(&self) -> stream::Config {
+        stream::Config {
+            name: self.stream_name(),
+            subjects: vec![
+                format!("$O.{}.C.>", self.name),
+                format!("$O.{}.M.>", self.name),
+            ],
+            discard: DiscardPolicy::New,
+            allow_rollup_hdrs: true,
+            allow_direct: true,
+            ..Default::default()
+        }
+    }
+
+    /// Chunk frames followed by the meta frame, all expecting a `PubAck`
+    /// on `inbox`. `nuid` has to be unique within the bucket, the chunks of
+    /// a previous version are left to a purge of their subject.
+    pub fn put(
+        &self,
+        info: ObjectInfo,
+        data: impl Into<Bytes>,
+        nuid: &str,
+        inbox: impl Into<Bytes>,
+    ) -> Result<(Vec<Message>, ObjectInfo), ObjectError> {
+        validate_name(&info.name)?;
+        validate_nuid(nuid)?;
+
+        let data = data.into();
+        let inbox = inbox.into();
+        let chunk_size = info
+            .options
+            .as_ref()
+            .and_then(|o| o.max_chunk_size)
+            .filter(|size| *size > 0)
+            .unwrap_or(DEFAULT_CHUNK_SIZE);
+
+        let subject = self.chunk_subject(nuid);
+        let mut hasher = Sha256::new();
+        let mut messages = Vec::with_capacity(data.len() / chunk_size + 2);
+
+        for start in (0..data.len()).step_by(chunk_size) {
+            let chunk = data.slice(start..data.len().min(start + chunk_size));
+
+            hasher.update(&chunk);
+            messages.push(Message::publish_with_reply(
+                subject.clone(),
+                inbox.clone(),
+                chunk,
+            ));
+        }
+
+        let info = ObjectInfo {
+            bucket: self.name.clone(),
+            nuid: nuid.to_string(),
+            size: data.len() as u64,
+            mtime: rfc3339(SystemTime::now()),
+            chunks: messages.len() as u64,
+            digest: Some(digest(hasher)),
+            deleted: false,
+            ..info
+        };
+
+        messages.push(self.meta_message(&info, inbox)?);
+
+        Ok((messages, info))
+    }
+
+    /// Meta of `name` pointing to the object `target`.
+    pub fn link(
+        &self,
+        name: &str,
+        target: &ObjectInfo,
+        nuid: &str,
+        inbox: impl Into<Bytes>,
+    ) -> Result<(Message, ObjectInfo), ObjectError> {
+        if target.deleted {
+            return Err(ObjectError::Deleted);
+        }
+        // links to links aren't followed
+        if target.link().is_some() {
+            return Err(ObjectError::Link);
+        }
+
+        self.link_with(
+            name,
+            ObjectLink {
+                bucket: target.bucket.clone(),
+                name: Some(target.name.clone()),
+            },
+            nuid,
+            inbox.into(),
+        )
+    }
+
+    /// Meta of `name` pointing to the whole `bucket`.
+    pub fn link_bucket(
+        &self,
+        name: &str,
+        bucket: &str,
+        nuid: &str,
+        inbox: impl Into<Bytes>,
+    ) -> Result<(Message, ObjectInfo), ObjectError> {
+        let bucket = Store::new(bucket)?;
+
+        self.link_with(
+            name,
+            ObjectLink {
+                bucket: bucket.name,
+                name: None,
+            },
+            nuid,
+            inbox.into(),
+        )
+    }
+
+    fn link_with(
+        &self,
+        name: &str,
+        link: ObjectLink,
+        nuid: &str,
+        inbox: Bytes,
+    ) -> Result<(Message, ObjectInfo), ObjectError> {
+        validate_name(name)?;
+        validate_nuid(nuid)?;
+
+        let info = ObjectInfo {
+            name: name.to_string(),
+            options: Some(ObjectOptions {
+                link: Some(link),
+                max_chunk_size: None,
+            }),
+            bucket: self.name.clone(),
+            nuid: nuid.to_string(),
+            mtime: rfc3339(SystemTime::now()),
+            ..Default::default()
+        };
+
+        Ok((self.meta_message(&info, inbox)?, info))
+    }
+
+    /// Meta marking the object deleted and the purge of its chunks.
+    pub fn delete(
+        &self,
+        info: &ObjectInfo,
+        inbox: impl Into<Bytes>,
+    ) -> Result<(Message, stream::PurgeRequest), ObjectError> {
+        validate_name(&info.name)?;
+
+        let deleted = ObjectInfo {
+            size: 0,
+            chunks: 0,
+            digest: None,
+            deleted: true,
+            mtime: rfc3339(SystemTime::now()),
+            ..info.clone()
+        };
+
+        let purge = stream::PurgeRequest {
+            name: self.stream_name(),
+            filter: Some(self.chunk_subject(&info.nuid)),
+            ..Default::default()
+        };
+
+        Ok((self.meta_message(&deleted, inbox.into())?, purge))
+    }
+
+    /// Latest meta of `name`, see [`Store::info_from_stored`].
+    pub fn info(&self, name: &str) -> Result<stream::MsgGetRequest, ObjectError> {
+        validate_name(name)?;
+
+        Ok(stream::MsgGetRequest {
+            stream: self.stream_name(),
+            last_by_subj: Some(self.meta_subject(name)),
+            ..Default::default()
+        })
+    }
+
+    pub fn info_from_stored(&self, stored: &StoredMessage) -> Result<ObjectInfo, ObjectError> {
+        let info = ObjectInfo::parse(&stored.data)?;
+
+        if stored.subject != self.meta_subject(&info.name) || info.bucket != self.name {
+            return Err(ObjectError::InvalidMeta);
+        }
+
+        Ok(info)
+    }
+
+    /// Ephemeral push consumer delivering the chunks of `info` in order to
+    /// `deliver_subject`, see [`ObjectReader`]. Objects without chunks don't
+    /// need one.
+    pub fn get(
+        &self,
+        info: &ObjectInfo,
+        deliver_subject: impl Into<String>,
+    ) -> Result<consumer::CreateRequest, ObjectError> {
+        validate_readable(info)?;
+
+        Ok(consumer::CreateRequest {
+            stream_name: self.stream_name(),
+            config: consumer::Config {
+                deliver_subject: Some(deliver_subject.into()),
+                deliver_policy: DeliverPolicy::All,
+                ack_policy: AckPolicy::None,
+                max_deliver: 1,
+                filter_subject: Some(self.chunk_subject(&info.nuid)),
+                mem_storage: true,
+                num_replicas: Some(1),
+                ..Default::default()
+            },
+        })
+    }
+
+    fn meta_message(&self, info: &ObjectInfo, inbox: Bytes) -> Result<Message, ObjectError> {
+        let mut headers = Headers::new();
+        // only the latest meta of the object is kept
+        headers.insert(ROLLUP, "sub");
+
+        let body = serde_json::to_vec(info).map_err(|_| ObjectError::InvalidMeta)?;
+
+        let subject = self.meta_subject(&info.name);
+
+        Message::hpublish_with_reply(subject, inbox, &headers, body).map_err(ObjectError::from)
+    }
+}
+
+impl ObjectInfo {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+
+        self
+    }
+
+    pub fn with_max_chunk_size(mut self, max_chunk_size: usize) -> Self {
+        self.options
+            .get_or_insert_with(Default::default)
+            .max_chunk_size = Some(max_chunk_size);
+
+        self
+    }
+
+    pub fn parse(body: &[u8]) -> Result<Self, ObjectError> {
+        serde_json::from_slice(body).map_err(|_| ObjectError::InvalidMeta)
+    }
+
+    pub fn link(&self) -> Option<&ObjectLink> {
+        self.options.as_ref().and_then(|o| o.link.as_ref())
+    }
+}
+
+/// Chunk deliveries of an object reassembled and checked against its meta.
+#[derive(Debug, Clone)]
+pub struct ObjectReader {
+    subject: String,
+    info: ObjectInfo,
+    data: BytesMut,
+    chunks: u64,
+    hasher: Sha256,
+}
+
+impl ObjectReader {
+    pub fn new(info: ObjectInfo) -> Result<Self, ObjectError> {
+        validate_readable(&info)?;
+
+        Ok(Self {
+            subject: Store::new(info.bucket.as_str())?.chunk_subject(&info.nuid),
+            // grows with the chunks, `size` is only trusted once they add up
+            data: BytesMut::new(),
+            chunks: 0,
+            hasher: Sha256::new(),
+            info,
+        })
+    }
+
+    pub fn info(&self) -> &ObjectInfo {
+        &self.info
+    }
+
+    /// Whether every chunk was received.
+    pub fn is_done(&self) -> bool {
+        self.chunks >= self.info.chunks
+    }
+
+    /// Appends a chunk, `true` once it was the last one.
+    pub fn push(&mut self, message: &Message) -> Result<bool, ObjectError> {
+        let chunk = match message {
+            Message::Message(m) if m.subject == self.subject.as_bytes() => {
+                m.payload.as_deref().unwrap_or_default()
+            }
+            _ => return Err(ObjectError::InvalidChunk),
+        };
+
+        let size = (self.data.len() + chunk.len()) as u64;
+
+        if self.is_done() || size > self.info.size {
+            return Err(ObjectError::SizeMismatch {
+                expected: self.info.size,
+                actual: size,
+            });
+        }
+
+        self.hasher.update(chunk);
+        self.data.extend_from_slice(chunk);
+        self.chunks += 1;
+
+        Ok(self.is_done())
+    }
+
+    /// Object data once its size and digest were checked.
+    pub fn finish(self) -> Result<Bytes, ObjectError> {
+        let size = self.data.len() as u64;
+
+        if !self.is_done() || size != self.info.size {
+            return Err(ObjectError::SizeMismatch {
+                expected: self.info.size,
+                actual: size,
+            });
+        }
+
+        if self.info.digest.as_deref() != Some(digest(self.hasher).as_str()) {
+            return Err(ObjectError::DigestMismatch);
+        }
+
+        Ok(self.data.freeze())
+    }
+}
+
+fn digest(hasher: Sha256) -> String {
+    format!("{}{}", DIGEST_PREFIX, URL_SAFE.encode(hasher.finalize()))
+}
+
+// deleted objects and links have no chunks to read
+fn validate_readable(info: &ObjectInfo) -> Result<(), ObjectError> {
+    if info.deleted {
+        return Err(ObjectError::Deleted);
+    }
+    if info.link().is_some() {
+        return Err(ObjectError::Link);
+    }
+
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<(), ObjectError> {
+    match name.is_empty() {
+        true => Err(ObjectError::InvalidName),
+        false => Ok(()),
+    }
+}
+
+// single subject token
+fn validate_nuid(nuid: &str) -> Result<(), ObjectError> {
+    let valid = !nuid.is_empty()
+        && nuid
+            .bytes()
+            .all(|c| c.is_ascii_graphic() && !matches!(c, b'.' | b'*' | b'>'));
+
+    match valid {
+        true => Ok(()),
+        false => Err(ObjectError::InvalidNuid(nuid.to_string())),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectError {
+    InvalidBucket(String),
+    InvalidName,
+    InvalidNuid(String),
+    InvalidMeta,
+    // message that isn't a chunk of the object
+    InvalidChunk,
+    Deleted,
+    // links have to be resolved through the linked bucket
+    Link,
+    SizeMismatch { expected: u64, actual: u64 },
+    DigestMismatch,
+    Protocol(ProtocolError),
+}
+
+impl fmt::Display for ObjectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidBucket(name) => write!(f, "invalid bucket name '{}'", name),
+            Self::InvalidName => write!(f, "empty object name"),
+            Self::InvalidNuid(nuid) => write!(f, "invalid object nuid '{}'", nuid),
+            Self::InvalidMeta => write!(f, "invalid object meta"),
+            Self::InvalidChunk => write!(f, "invalid object chunk"),
+            Self::Deleted => write!(f, "object is deleted"),
+            Self::Link => write!(f, "object is a link"),
+            Self::SizeMismatch { expected, actual } => {
+                write!(f, "object size {} does not match meta {}", actual, expected)
+            }
+            Self::DigestMismatch => write!(f, "object digest does not match meta"),
+            Self::Protocol(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for ObjectError {}
+
+impl From<ProtocolError> for ObjectError {
+    fn from(e: ProtocolError) -> Self {
+        Self::Protocol(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::message::message;
+
+    // chunk as the server would deliver it to the `get` consumer
+    fn as_delivery(publish: &Message) -> Message {
+        Message::Message(message::Payload {
+            subject: publish.subject().unwrap().clone(),
+            sid: 1,
+            reply_to: None,
+            payload_size: publish.body_size().unwrap(),
+            payload: publish.body().cloned(),
+        })
+    }
+
+    #[test]
+    fn test_put() {
+        let store = Store::new("files").unwrap();
+        let info = ObjectInfo::new("report.pdf")
+            .with_description("q1")
+            .with_max_chunk_size(4);
+
+        let data = Bytes::from("0123456789");
+        let (messages, info) = store.put(info, data.clone(), "N1", "_INBOX.1").unwrap();
+
+        assert_eq!(4, messages.len());
+        assert_eq!("PUB $O.files.C.N1 _INBOX.1 4", messages[0].to_string());
+        assert_eq!(Some(&Bytes::from("89")), messages[2].body());
+        // chunks are slices of the data
+        assert_eq!(data[4..].as_ptr(), messages[1].body().unwrap().as_ptr());
+
+        assert_eq!(10, info.size);
+        assert_eq!(3, info.chunks);
+        assert_eq!(
+            Some("SHA-256=hNiYd_DUBB77a_kaFvAkjy_Vc-avBcGflr7bn4gveII="),
+            info.digest.as_deref()
+        );
+
+        let meta = &messages[3];
+
+        assert_eq!(
+            Some(&Bytes::from("$O.files.M.cmVwb3J0LnBkZg==")),
+            meta.subject()
+        );
+        assert_eq!(Some("sub"), meta.headers().unwrap().get("Nats-Rollup"));
+        assert_eq!(info, ObjectInfo::parse(meta.body().unwrap()).unwrap());
+
+        let (messages, info) = store
+            .put(ObjectInfo::new("empty"), "", "N2", "_INBOX.1")
+            .unwrap();
+
+        assert_eq!(1, messages.len());
+        assert_eq!(0, info.chunks);
+
+        assert_eq!(
+            Err(ObjectError::InvalidNuid("N.1".into())),
+            store.put(ObjectInfo::new("a"), "", "N.1", "_INBOX.1")
+        );
+        assert_eq!(
+            Err(ObjectError::InvalidName),
+            store.put(ObjectInfo::new(""), "", "N1", "_INBOX.1")
+        );
+    }
+
+    #[test]
+    fn test_meta() {
+        let body = br#"{"name":"report.pdf","bucket":"files","nuid":"N1","size":10,"mtime":"2023-03-01T10:15:31.5Z","chunks":3,"digest":"SHA-256=abc","options":{"max_chunk_size":4}}"#;
+        let info = ObjectInfo::parse(body).unwrap();
+
+        assert_eq!(Some(4), info.options.as_ref().unwrap().max_chunk_size);
+        assert!(!info.deleted);
+
+        let store = Store::new("files").unwrap();
+        let stored = StoredMessage {
+            subject: "$O.files.M.cmVwb3J0LnBkZg==".into(),
+            seq: 4,
+            hdrs: None,
+            data: Bytes::from_static(body),
+            time: "2023-03-01T10:15:31.5Z".into(),
+        };
+
+        assert_eq!(info, store.info_from_stored(&stored).unwrap());
+        assert_eq!(
+            Some("$O.files.M.cmVwb3J0LnBkZg=="),
+            store.info("report.pdf").unwrap().last_by_subj.as_deref()
+        );
+
+        let (message, purge) = store.delete(&info, "_INBOX.1").unwrap();
+        let deleted = ObjectInfo::parse(message.body().unwrap()).unwrap();
+
+        assert!(deleted.deleted);
+        assert_eq!((0, 0, None), (deleted.size, deleted.chunks, deleted.digest));
+        assert_eq!(Some("$O.files.C.N1"), purge.filter.as_deref());
+
+        let (_, link) = store.link("latest", &info, "N3", "_INBOX.1").unwrap();
+
+        assert_eq!(
+            Some(&ObjectLink {
+                bucket: "files".into(),
+                name: Some("report.pdf".into()),
+            }),
+            link.link()
+        );
+        assert_eq!(
+            Some(&ObjectError::Link),
+            store
+                .link("latest2", &link, "N4", "_INBOX.1")
+                .err()
+                .as_ref()
+        );
+        assert_eq!(
+            Err(ObjectError::Link),
+            store.get(&link, "_INBOX.d").map(|_| ())
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let store = Store::new("files").unwrap();
+        let info = ObjectInfo::new("report.pdf").with_max_chunk_size(4);
+        let (messages, info) = store.put(info, "0123456789", "N1", "_INBOX.1").unwrap();
+
+        let get = store.get(&info, "_INBOX.d").unwrap();
+
+        assert_eq!(Some("$O.files.C.N1"), get.config.filter_subject.as_deref());
+
+        let mut reader = ObjectReader::new(info.clone()).unwrap();
+
+        assert!(!reader.push(&as_delivery(&messages[0])).unwrap());
+        assert!(!reader.push(&as_delivery(&messages[1])).unwrap());
+        assert!(reader.push(&as_delivery(&messages[2])).unwrap());
+        assert_eq!(Bytes::from("0123456789"), reader.finish().unwrap());
+
+        // meta frame, not a chunk
+        let mut reader = ObjectReader::new(info.clone()).unwrap();
+
+        assert_eq!(
+            Err(ObjectError::InvalidChunk),
+            reader.push(&as_delivery(&messages[3]))
+        );
+
+        let mut reader = ObjectReader::new(ObjectInfo {
+            digest: Some("SHA-256=abc".into()),
+            ..info.clone()
+        })
+        .unwrap();
+
+        for message in &messages[..3] {
+            reader.push(&as_delivery(message)).unwrap();
+        }
+
+        assert_eq!(Err(ObjectError::DigestMismatch), reader.finish());
+
+        let mut reader = ObjectReader::new(ObjectInfo { size: 6, ..info }).unwrap();
+
+        reader.push(&as_delivery(&messages[0])).unwrap();
+
+        assert_eq!(
+            Err(ObjectError::SizeMismatch {
+                expected: 6,
+                actual: 8
+            }),
+            reader.push(&as_delivery(&messages[1]))
+        );
+
+        let (_, info) = store
+            .put(ObjectInfo::new("empty"), "", "N2", "_INBOX.1")
+            .unwrap();
+        let reader = ObjectReader::new(info).unwrap();
+
+        assert!(reader.is_done());
+        assert_eq!(Bytes::new(), reader.finish().unwrap());
+    }
+}
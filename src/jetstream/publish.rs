@@ -0,0 +1,284 @@
+use std::{error, fmt};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use super::{response, ApiError, Error, ErrorCode};
+use crate::message::{Headers, Message};
+use crate::ProtocolError;
+
+pub const MSG_ID: &str = "Nats-Msg-Id";
+pub const EXPECTED_STREAM: &str = "Nats-Expected-Stream";
+pub const EXPECTED_LAST_SEQUENCE: &str = "Nats-Expected-Last-Sequence";
+pub const EXPECTED_LAST_SUBJECT_SEQUENCE: &str = "Nats-Expected-Last-Subject-Sequence";
+pub const EXPECTED_LAST_MSG_ID: &str = "Nats-Expected-Last-Msg-Id";
+
+/// Deduplication id and optimistic concurrency checks of a stream publish.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PublishOptions {
+    // duplicates within the stream `duplicate_window` are dropped
+    pub msg_id: Option<String>,
+    pub expected_stream: Option<String>,
+    pub expected_last_sequence: Option<u64>,
+    pub expected_last_subject_sequence: Option<u64>,
+    pub expected_last_msg_id: Option<String>,
+}
+
+impl PublishOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_msg_id(mut self, msg_id: impl Into<String>) -> Self {
+        self.msg_id = Some(msg_id.into());
+
+        self
+    }
+
+    pub fn with_expected_stream(mut self, stream: impl Into<String>) -> Self {
+        self.expected_stream = Some(stream.into());
+
+        self
+    }
+
+    pub fn with_expected_last_sequence(mut self, seq: u64) -> Self {
+        self.expected_last_sequence = Some(seq);
+
+        self
+    }
+
+    pub fn with_expected_last_subject_sequence(mut self, seq: u64) -> Self {
+        self.expected_last_subject_sequence = Some(seq);
+
+        self
+    }
+
+    pub fn with_expected_last_msg_id(mut self, msg_id: impl Into<String>) -> Self {
+        self.expected_last_msg_id = Some(msg_id.into());
+
+        self
+    }
+
+    /// Sets the headers of the options that are set, replacing existing values.
+    pub fn apply(&self, headers: &mut Headers) {
+        if let Some(msg_id) = &self.msg_id {
+            headers.insert(MSG_ID, msg_id.as_str());
+        }
+        if let Some(stream) = &self.expected_stream {
+            headers.insert(EXPECTED_STREAM, stream.as_str());
+        }
+        if let Some(seq) = self.expected_last_sequence {
+            headers.insert(EXPECTED_LAST_SEQUENCE, seq.to_string());
+        }
+        if let Some(seq) = self.expected_last_subject_sequence {
+            headers.insert(EXPECTED_LAST_SUBJECT_SEQUENCE, seq.to_string());
+        }
+        if let Some(msg_id) = &self.expected_last_msg_id {
+            headers.insert(EXPECTED_LAST_MSG_ID, msg_id.as_str());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Publish expecting a `PubAck` on `inbox`, HPUB when any option is set.
+    ///
+    /// Fails for a message id or stream name spanning lines.
+    pub fn to_message(
+        &self,
+        subject: impl Into<Bytes>,
+        inbox: impl Into<Bytes>,
+        body: impl Into<Bytes>,
+    ) -> Result<Message, ProtocolError> {
+        if self.is_empty() {
+            return Ok(Message::publish_with_reply(subject, inbox, body));
+        }
+
+        let mut headers = Headers::new();
+        self.apply(&mut headers);
+
+        Message::hpublish_with_reply(subject, inbox, &headers, body)
+    }
+}
+
+/// Stream acknowledgement of a publish.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PubAck {
+    pub stream: String,
+    pub seq: u64,
+    // `msg_id` was seen within the duplicate window, nothing was stored
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub duplicate: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+}
+
+impl PubAck {
+    pub fn parse(body: &[u8]) -> Result<Self, PublishError> {
+        response(body).map_err(PublishError::from)
+    }
+
+    /// Reply to a stream publish, the 503 status of a subject no stream
+    /// listens on included.
+    pub fn from_message(message: &Message) -> Result<Self, PublishError> {
+        if let Some(block) = message.header_block() {
+            let headers = Headers::decode(block).map_err(|_| PublishError::InvalidResponse)?;
+
+            if headers.status == Some(503) {
+                return Err(PublishError::NoResponders);
+            }
+        }
+
+        Self::parse(message.body().map_or(&[][..], |b| &b[..]))
+    }
+}
+
+/// Publish rejected by the stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PublishError {
+    // no stream listens on the subject
+    NoResponders,
+    // `Nats-Expected-Last-(Subject-)Sequence` check failed, `current` is the
+    // sequence the server reported
+    WrongLastSequence { current: Option<u64> },
+    WrongLastMsgId,
+    // `Nats-Expected-Stream` doesn't match the stream listening on the subject
+    StreamMismatch,
+    Api(ApiError),
+    InvalidResponse,
+}
+
+impl From<Error> for PublishError {
+    fn from(e: Error) -> Self {
+        let e = match e {
+            Error::Api(e) => e,
+            _ => return Self::InvalidResponse,
+        };
+
+        match e.err_code {
+            ErrorCode::StreamWrongLastSequence => Self::WrongLastSequence {
+                // "wrong last sequence: 5"
+                current: e
+                    .description
+                    .rsplit(':')
+                    .next()
+                    .and_then(|seq| seq.trim().parse().ok()),
+            },
+            ErrorCode::StreamWrongLastMsgId => Self::WrongLastMsgId,
+            ErrorCode::StreamMismatch => Self::StreamMismatch,
+            _ => Self::Api(e),
+        }
+    }
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoResponders => write!(f, "no stream listens on the subject"),
+            Self::WrongLastSequence { current: Some(seq) } => {
+                write!(f, "wrong last sequence: {}", seq)
+            }
+            Self::WrongLastSequence { current: None } => write!(f, "wrong last sequence"),
+            Self::WrongLastMsgId => write!(f, "wrong last msg id"),
+            Self::StreamMismatch => write!(f, "expected stream does not match"),
+            Self::Api(e) => e.fmt(f),
+            Self::InvalidResponse => write!(f, "invalid publish acknowledgement"),
+        }
+    }
+}
+
+impl error::Error for PublishError {}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::{PubAck, PublishError, PublishOptions};
+    use crate::message::{hmessage, Message};
+    use crate::ProtocolError;
+
+    #[test]
+    fn test_options() {
+        let message = PublishOptions::new()
+            .to_message("orders.eu", "_INBOX.1", "hello")
+            .unwrap();
+
+        assert_eq!("PUB orders.eu _INBOX.1 5", message.to_string());
+
+        let options = PublishOptions::new()
+            .with_msg_id("order-1")
+            .with_expected_stream("ORDERS")
+            .with_expected_last_subject_sequence(7);
+
+        let message = options
+            .to_message("orders.eu", "_INBOX.1", "hello")
+            .unwrap();
+        let headers = message.headers().unwrap();
+
+        assert_eq!(Ok(()), message.validate());
+        assert_eq!(Some("order-1"), headers.get("Nats-Msg-Id"));
+        assert_eq!(Some("ORDERS"), headers.get("Nats-Expected-Stream"));
+        assert_eq!(
+            Some("7"),
+            headers.get("Nats-Expected-Last-Subject-Sequence")
+        );
+        assert_eq!(None, headers.get("Nats-Expected-Last-Sequence"));
+        assert_eq!(3, headers.len());
+
+        let options = PublishOptions::new().with_msg_id("a\r\nNats-Rollup: all");
+
+        assert_eq!(
+            Err(ProtocolError::InvalidHeader("Nats-Msg-Id".into())),
+            options.to_message("orders.eu", "_INBOX.1", "hello")
+        );
+    }
+
+    #[test]
+    fn test_pub_ack() {
+        let ack = PubAck::parse(br#"{"stream":"ORDERS","seq":12,"domain":"hub"}"#).unwrap();
+
+        assert_eq!("ORDERS", ack.stream);
+        assert_eq!(12, ack.seq);
+        assert!(!ack.duplicate);
+        assert_eq!(Some("hub"), ack.domain.as_deref());
+
+        let ack = PubAck::parse(br#"{"stream":"ORDERS","seq":12,"duplicate":true}"#).unwrap();
+
+        assert!(ack.duplicate);
+
+        let cases = [
+            (
+                &br#"{"error":{"code":400,"err_code":10071,"description":"wrong last sequence: 5"}}"#[..],
+                PublishError::WrongLastSequence { current: Some(5) },
+            ),
+            (
+                br#"{"error":{"code":400,"err_code":10070,"description":"wrong last msg ID: order-0"}}"#,
+                PublishError::WrongLastMsgId,
+            ),
+            (
+                br#"{"error":{"code":400,"err_code":10060,"description":"expected stream does not match"}}"#,
+                PublishError::StreamMismatch,
+            ),
+            (br#"{"stream":"ORDERS"}"#, PublishError::InvalidResponse),
+        ];
+
+        for (body, expected) in cases {
+            assert_eq!(Err(expected), PubAck::parse(body));
+        }
+
+        let mut no_responders = Message::HMessage(hmessage::Payload {
+            subject: Bytes::from("_INBOX.1"),
+            sid: 1,
+            headers: Some(Bytes::from("NATS/1.0 503\r\n\r\n")),
+            payload: Some(Bytes::new()),
+            ..Default::default()
+        });
+        no_responders.derive_sizes();
+
+        assert_eq!(
+            Err(PublishError::NoResponders),
+            PubAck::from_message(&no_responders)
+        );
+    }
+}
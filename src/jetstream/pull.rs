@@ -0,0 +1,255 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::{name, Error, Request};
+use crate::error::ProtocolError;
+use crate::message::Message;
+use crate::time::nanos;
+
+/// `CONSUMER.MSG.NEXT` request of a pull consumer. Messages and status
+/// messages are delivered to the reply subject of the request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NextRequest {
+    #[serde(skip)]
+    pub stream: String,
+    #[serde(skip)]
+    pub consumer: String,
+    pub batch: usize,
+    // nanoseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<usize>,
+    // nanoseconds, requires `expires`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_heartbeat: Option<u64>,
+    // 404 right away instead of waiting when nothing is pending
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub no_wait: bool,
+}
+
+impl NextRequest {
+    /// Single message, no expiry.
+    pub fn new(stream: impl Into<String>, consumer: impl Into<String>) -> Self {
+        Self {
+            stream: stream.into(),
+            consumer: consumer.into(),
+            batch: 1,
+            expires: None,
+            max_bytes: None,
+            idle_heartbeat: None,
+            no_wait: false,
+        }
+    }
+
+    pub fn with_batch(mut self, batch: usize) -> Self {
+        self.batch = batch;
+
+        self
+    }
+
+    pub fn with_expires(mut self, expires: Duration) -> Self {
+        self.expires = Some(nanos(expires));
+
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+
+        self
+    }
+
+    pub fn with_idle_heartbeat(mut self, idle_heartbeat: Duration) -> Self {
+        self.idle_heartbeat = Some(nanos(idle_heartbeat));
+
+        self
+    }
+
+    pub fn with_no_wait(mut self, no_wait: bool) -> Self {
+        self.no_wait = no_wait;
+
+        self
+    }
+}
+
+impl Request for NextRequest {
+    // deliveries and status messages instead of a JSON response
+    type Response = ();
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!(
+            "CONSUMER.MSG.NEXT.{}.{}",
+            name(&self.stream)?,
+            name(&self.consumer)?
+        ))
+    }
+}
+
+/// Status message sent to a pull request inbox instead of a stream message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    // 404, `no_wait` request with nothing pending
+    NoMessages,
+    // 408, `expires` elapsed
+    RequestTimeout,
+    // 409, next message is larger than `max_bytes`
+    MaxBytes,
+    // 409
+    LeadershipChange,
+    // 409
+    ConsumerDeleted,
+    // 409 with another description, `Exceeded MaxWaiting` and the like
+    Conflict(String),
+    // 100, sent every `idle_heartbeat` while the request is pending
+    IdleHeartbeat,
+    // 100, push consumers with flow control
+    FlowControl,
+    Other {
+        code: u16,
+        description: Option<String>,
+    },
+}
+
+impl Status {
+    /// `None` for a regular message, HMSG without inline status included.
+    pub fn from_message(message: &Message) -> Result<Option<Self>, ProtocolError> {
+        if message.header_block().is_none() {
+            return Ok(None);
+        }
+
+        let headers = message.headers()?;
+
+        let code = match headers.status {
+            Some(code) => code,
+            None => return Ok(None),
+        };
+
+        let description = headers.description.unwrap_or_default();
+        let is = |s: &str| description.eq_ignore_ascii_case(s);
+
+        Ok(Some(match code {
+            404 => Self::NoMessages,
+            408 => Self::RequestTimeout,
+            409 if is("Message Size Exceeds MaxBytes") => Self::MaxBytes,
+            409 if is("Leadership Change") => Self::LeadershipChange,
+            409 if is("Consumer Deleted") => Self::ConsumerDeleted,
+            409 => Self::Conflict(description),
+            100 if is("FlowControl Request") => Self::FlowControl,
+            100 => Self::IdleHeartbeat,
+            code => Self::Other {
+                code,
+                description: Some(description).filter(|d| !d.is_empty()),
+            },
+        }))
+    }
+
+    /// Whether the pull request is over, heartbeats keep it alive.
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, Self::IdleHeartbeat | Self::FlowControl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::Bytes;
+
+    use super::{NextRequest, Request, Status};
+    use crate::message::{hmessage, Message};
+
+    fn status(headers: &'static str) -> Message {
+        let mut message = Message::HMessage(hmessage::Payload {
+            subject: Bytes::from("_INBOX.1"),
+            sid: 1,
+            headers: Some(Bytes::from(headers)),
+            payload: Some(Bytes::new()),
+            ..Default::default()
+        });
+        message.derive_sizes();
+
+        message
+    }
+
+    #[test]
+    fn test_next_request() {
+        let request = NextRequest::new("ORDERS", "processor")
+            .with_batch(10)
+            .with_expires(Duration::from_secs(30))
+            .with_idle_heartbeat(Duration::from_secs(5))
+            .with_max_bytes(1024);
+
+        let message = request.to_message("$JS.API", "_INBOX.1").unwrap();
+
+        assert_eq!(
+            Some(&Bytes::from("$JS.API.CONSUMER.MSG.NEXT.ORDERS.processor")),
+            message.subject()
+        );
+        assert_eq!(
+            Some(&Bytes::from(
+                "{\"batch\":10,\"expires\":30000000000,\"max_bytes\":1024,\"idle_heartbeat\":5000000000}"
+            )),
+            message.body()
+        );
+
+        let request = NextRequest::new("ORDERS", "processor").with_no_wait(true);
+
+        assert_eq!(
+            "{\"batch\":1,\"no_wait\":true}",
+            serde_json::to_string(&request).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_status() {
+        let cases = [
+            ("NATS/1.0 404 No Messages\r\n\r\n", Status::NoMessages),
+            (
+                "NATS/1.0 408 Request Timeout\r\nNats-Pending-Messages: 1\r\n\r\n",
+                Status::RequestTimeout,
+            ),
+            (
+                "NATS/1.0 409 Message Size Exceeds MaxBytes\r\n\r\n",
+                Status::MaxBytes,
+            ),
+            (
+                "NATS/1.0 409 Leadership Change\r\n\r\n",
+                Status::LeadershipChange,
+            ),
+            (
+                "NATS/1.0 409 Exceeded MaxWaiting\r\n\r\n",
+                Status::Conflict("Exceeded MaxWaiting".into()),
+            ),
+            ("NATS/1.0 100 Idle Heartbeat\r\n\r\n", Status::IdleHeartbeat),
+            (
+                "NATS/1.0 503\r\n\r\n",
+                Status::Other {
+                    code: 503,
+                    description: None,
+                },
+            ),
+        ];
+
+        for (headers, expected) in cases {
+            assert_eq!(
+                Some(expected),
+                Status::from_message(&status(headers)).unwrap()
+            );
+        }
+
+        assert!(!Status::IdleHeartbeat.is_terminal());
+        assert!(Status::NoMessages.is_terminal());
+
+        assert_eq!(
+            None,
+            Status::from_message(&status("NATS/1.0\r\nFoo: bar\r\n\r\n")).unwrap()
+        );
+        assert_eq!(
+            None,
+            Status::from_message(&Message::publish("FOO", "")).unwrap()
+        );
+        assert!(Status::from_message(&status("HTTP/1.1 200 OK\r\n\r\n")).is_err());
+    }
+}
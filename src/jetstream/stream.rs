@@ -0,0 +1,501 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use super::{name, nullable, Error, Paging, Request, SuccessResponse};
+use crate::error::ProtocolError;
+use crate::message::Headers;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionPolicy {
+    #[default]
+    Limits,
+    Interest,
+    WorkQueue,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscardPolicy {
+    #[default]
+    Old,
+    New,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageType {
+    #[default]
+    File,
+    Memory,
+}
+
+/// Stream configuration, limits of `-1` are unlimited and durations are in
+/// nanoseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(deserialize_with = "nullable")]
+    pub subjects: Vec<String>,
+    pub retention: RetentionPolicy,
+    pub max_consumers: i64,
+    pub max_msgs: i64,
+    pub max_bytes: i64,
+    pub max_age: i64,
+    pub max_msgs_per_subject: i64,
+    pub max_msg_size: i64,
+    pub discard: DiscardPolicy,
+    pub storage: StorageType,
+    pub num_replicas: usize,
+    pub duplicate_window: i64,
+    pub no_ack: bool,
+    pub allow_rollup_hdrs: bool,
+    pub deny_delete: bool,
+    pub deny_purge: bool,
+    pub allow_direct: bool,
+    pub sealed: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            description: None,
+            subjects: Vec::new(),
+            retention: RetentionPolicy::Limits,
+            max_consumers: -1,
+            max_msgs: -1,
+            max_bytes: -1,
+            max_age: 0,
+            max_msgs_per_subject: -1,
+            max_msg_size: -1,
+            discard: DiscardPolicy::Old,
+            storage: StorageType::File,
+            num_replicas: 1,
+            duplicate_window: 0,
+            no_ack: false,
+            allow_rollup_hdrs: false,
+            deny_delete: false,
+            deny_purge: false,
+            allow_direct: false,
+            sealed: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub messages: u64,
+    pub bytes: u64,
+    pub first_seq: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_ts: Option<String>,
+    pub last_seq: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_ts: Option<String>,
+    pub num_deleted: u64,
+    pub num_subjects: u64,
+    pub consumer_count: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerInfo {
+    pub name: String,
+    pub current: bool,
+    pub offline: bool,
+    // nanoseconds since the peer was last seen
+    pub active: i64,
+    pub lag: u64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClusterInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub replicas: Vec<PeerInfo>,
+}
+
+/// Response of stream CREATE/UPDATE/INFO.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Info {
+    pub config: Config,
+    // RFC 3339 timestamp
+    pub created: String,
+    pub state: State,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<ClusterInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CreateRequest(pub Config);
+
+impl Request for CreateRequest {
+    type Response = Info;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!("STREAM.CREATE.{}", name(&self.0.name)?))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpdateRequest(pub Config);
+
+impl Request for UpdateRequest {
+    type Response = Info;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!("STREAM.UPDATE.{}", name(&self.0.name)?))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct InfoRequest {
+    #[serde(skip)]
+    pub name: String,
+    // only subjects matching the filter are listed in the state
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subjects_filter: Option<String>,
+}
+
+impl InfoRequest {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            subjects_filter: None,
+        }
+    }
+}
+
+impl Request for InfoRequest {
+    type Response = Info;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!("STREAM.INFO.{}", name(&self.name)?))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct DeleteRequest {
+    #[serde(skip)]
+    pub name: String,
+}
+
+impl Request for DeleteRequest {
+    type Response = SuccessResponse;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!("STREAM.DELETE.{}", name(&self.name)?))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ListRequest {
+    pub offset: usize,
+    // streams listening on a subject
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListResponse {
+    #[serde(flatten)]
+    pub paging: Paging,
+    #[serde(default, deserialize_with = "nullable")]
+    pub streams: Vec<Info>,
+}
+
+impl Request for ListRequest {
+    type Response = ListResponse;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok("STREAM.LIST".to_string())
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct NamesRequest {
+    pub offset: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamesResponse {
+    #[serde(flatten)]
+    pub paging: Paging,
+    #[serde(default, deserialize_with = "nullable")]
+    pub streams: Vec<String>,
+}
+
+impl Request for NamesRequest {
+    type Response = NamesResponse;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok("STREAM.NAMES".to_string())
+    }
+}
+
+/// Purges the whole stream unless narrowed by `filter`, `seq` or `keep`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct PurgeRequest {
+    #[serde(skip)]
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    // purge up to, not including, this sequence
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    // messages to keep
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep: Option<u64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PurgeResponse {
+    pub success: bool,
+    pub purged: u64,
+}
+
+impl Request for PurgeRequest {
+    type Response = PurgeResponse;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!("STREAM.PURGE.{}", name(&self.name)?))
+    }
+}
+
+/// Message by sequence, or the last/next one on a subject.
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct MsgGetRequest {
+    #[serde(skip)]
+    pub stream: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_by_subj: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_by_subj: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredMessage {
+    pub subject: String,
+    pub seq: u64,
+    // raw header block, base64 on the wire
+    #[serde(
+        default,
+        with = "super::base64::option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub hdrs: Option<Bytes>,
+    #[serde(default, with = "super::base64")]
+    pub data: Bytes,
+    pub time: String,
+}
+
+impl StoredMessage {
+    pub fn headers(&self) -> Result<Headers, ProtocolError> {
+        match &self.hdrs {
+            Some(hdrs) => Headers::decode(hdrs),
+            None => Ok(Headers::new()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MsgGetResponse {
+    pub message: StoredMessage,
+}
+
+impl Request for MsgGetRequest {
+    type Response = MsgGetResponse;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!("STREAM.MSG.GET.{}", name(&self.stream)?))
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct MsgDeleteRequest {
+    #[serde(skip)]
+    pub stream: String,
+    pub seq: u64,
+    // remove without overwriting the data
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub no_erase: bool,
+}
+
+impl Request for MsgDeleteRequest {
+    type Response = SuccessResponse;
+
+    fn subject(&self) -> Result<String, Error> {
+        Ok(format!("STREAM.MSG.DELETE.{}", name(&self.stream)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::response;
+    use super::*;
+
+    const INFO: &str = r#"{
+        "type": "io.nats.jetstream.api.v1.stream_info_response",
+        "config": {
+            "name": "ORDERS",
+            "subjects": ["orders.>"],
+            "retention": "workqueue",
+            "max_consumers": -1,
+            "max_msgs": -1,
+            "max_bytes": -1,
+            "max_age": 86400000000000,
+            "max_msgs_per_subject": -1,
+            "max_msg_size": -1,
+            "discard": "old",
+            "storage": "memory",
+            "num_replicas": 3,
+            "duplicate_window": 120000000000,
+            "allow_direct": true,
+            "mirror_direct": false,
+            "sealed": false,
+            "deny_delete": false,
+            "deny_purge": false,
+            "allow_rollup_hdrs": false
+        },
+        "created": "2023-03-01T10:15:30.123456789Z",
+        "state": {
+            "messages": 42,
+            "bytes": 4200,
+            "first_seq": 10,
+            "first_ts": "2023-03-01T10:15:31.5Z",
+            "last_seq": 51,
+            "last_ts": "2023-03-01T11:00:00Z",
+            "num_subjects": 3,
+            "consumer_count": 2
+        },
+        "cluster": {
+            "name": "east",
+            "leader": "n1",
+            "replicas": [
+                {"name": "n2", "current": true, "active": 12345, "lag": 0},
+                {"name": "n3", "current": false, "offline": true, "active": 0, "lag": 7}
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_info() {
+        let info: Info = response(INFO.as_bytes()).unwrap();
+
+        assert_eq!("ORDERS", info.config.name);
+        assert_eq!(vec!["orders.>".to_string()], info.config.subjects);
+        assert_eq!(RetentionPolicy::WorkQueue, info.config.retention);
+        assert_eq!(StorageType::Memory, info.config.storage);
+        assert_eq!(86_400_000_000_000, info.config.max_age);
+        assert_eq!(3, info.config.num_replicas);
+        assert_eq!(42, info.state.messages);
+        assert_eq!(51, info.state.last_seq);
+        assert_eq!(0, info.state.num_deleted);
+
+        let cluster = info.cluster.as_ref().unwrap();
+
+        assert_eq!(Some("n1"), cluster.leader.as_deref());
+        assert_eq!(7, cluster.replicas[1].lag);
+        assert!(cluster.replicas[1].offline);
+
+        let json = serde_json::to_vec(&info).unwrap();
+
+        assert_eq!(info, response::<Info>(&json).unwrap());
+    }
+
+    #[test]
+    fn test_requests() {
+        let create = CreateRequest(Config {
+            name: "ORDERS".into(),
+            subjects: vec!["orders.*".into()],
+            ..Default::default()
+        });
+
+        assert_eq!("STREAM.CREATE.ORDERS", create.subject().unwrap());
+
+        let json: serde_json::Value = serde_json::to_value(&create).unwrap();
+
+        assert_eq!("ORDERS", json["name"]);
+        assert_eq!(-1, json["max_msgs"]);
+        assert_eq!(create.0, serde_json::from_value::<Config>(json).unwrap());
+
+        let purge = PurgeRequest {
+            name: "ORDERS".into(),
+            filter: Some("orders.eu".into()),
+            keep: Some(5),
+            ..Default::default()
+        };
+
+        assert_eq!("STREAM.PURGE.ORDERS", purge.subject().unwrap());
+        assert_eq!(
+            r#"{"filter":"orders.eu","keep":5}"#,
+            serde_json::to_string(&purge).unwrap()
+        );
+
+        let delete = MsgDeleteRequest {
+            stream: "ORDERS".into(),
+            seq: 7,
+            no_erase: false,
+        };
+
+        assert_eq!("STREAM.MSG.DELETE.ORDERS", delete.subject().unwrap());
+        assert_eq!(r#"{"seq":7}"#, serde_json::to_string(&delete).unwrap());
+
+        for name in ["", "ORDERS.EU", "ORDERS.*", "ORDERS >", "ORDERS/EU"] {
+            assert!(matches!(
+                InfoRequest::new(name).subject(),
+                Err(Error::InvalidName(n)) if n == name
+            ));
+        }
+    }
+
+    #[test]
+    fn test_names_and_list() {
+        let names: NamesResponse = response(
+            br#"{"type":"io.nats.jetstream.api.v1.stream_names_response","total":0,"offset":0,"limit":1024,"streams":null}"#,
+        )
+        .unwrap();
+
+        assert_eq!(1024, names.paging.limit);
+        assert!(names.streams.is_empty());
+
+        let list = format!(
+            r#"{{"type":"io.nats.jetstream.api.v1.stream_list_response","total":1,"offset":0,"limit":256,"streams":[{}]}}"#,
+            INFO
+        );
+        let list: ListResponse = response(list.as_bytes()).unwrap();
+
+        assert_eq!(1, list.paging.total);
+        assert_eq!("ORDERS", list.streams[0].config.name);
+    }
+
+    #[test]
+    fn test_msg_get() {
+        let get: MsgGetResponse = response(
+            br#"{"type":"io.nats.jetstream.api.v1.stream_msg_get_response","message":{"subject":"orders.eu","seq":12,"hdrs":"TkFUUy8xLjANCkZvbzogYmFyDQoNCg==","data":"aGVsbG8=","time":"2023-03-01T10:15:31.5Z"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!("orders.eu", get.message.subject);
+        assert_eq!(12, get.message.seq);
+        assert_eq!(Bytes::from("hello"), get.message.data);
+        assert_eq!(Some("bar"), get.message.headers().unwrap().get("Foo"));
+
+        let json = serde_json::to_vec(&get).unwrap();
+
+        assert_eq!(get, response::<MsgGetResponse>(&json).unwrap());
+    }
+}
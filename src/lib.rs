@@ -1,7 +1,35 @@
+mod batch;
 mod codec;
+mod error;
+mod frame;
+mod metrics;
+#[cfg(any(feature = "jetstream", feature = "service"))]
+mod name;
 mod parser;
+#[cfg(all(test, any(feature = "jetstream", feature = "service")))]
+mod test_support;
+#[cfg(any(feature = "jetstream", feature = "service"))]
+mod time;
+mod tokenizer;
+mod vectored;
 
+#[cfg(feature = "jetstream")]
+pub mod jetstream;
 pub mod message;
+#[cfg(feature = "route")]
+pub mod route;
+#[cfg(feature = "service")]
+pub mod service;
 
+pub use batch::BatchStream;
 pub use codec::Codec;
-pub use message::Message;
+pub use error::ProtocolError;
+pub use frame::{decode_frame, Frame};
+pub use message::{Headers, Message};
+pub use metrics::{CodecMetrics, Counters, NoopMetrics};
+#[doc(hidden)]
+pub use parser::bench;
+pub use parser::ParseMode;
+#[cfg(feature = "route")]
+pub use route::{RouteCodec, RouteMessage};
+pub use vectored::{Chunks, VectoredSink};
@@ -1,6 +1,6 @@
 use std::io;
 
-use bytes::BufMut;
+use bytes::{BufMut, Bytes};
 
 static UNKNOWN_PROTOCOL_OPERATION: &[u8] = "Unknown Protocol Operation".as_bytes();
 static ATTEMPTED_TO_CONNECT_TO_ROUTE_PORT: &[u8] = "Attempted To Connect To Route Port".as_bytes();
@@ -20,13 +20,13 @@ static PERMISSIONS_VIOLATION_FOR_SUBSCRIPTION: &[u8] =
 static PERMISSIONS_VIOLATION_FOR_PUBLISH_TO: &[u8] =
     "Permissions Violation for Publish to ".as_bytes();
 
-const HEADER: &'static [u8] = &[b'-', b'E', b'R', b'R', b' '];
-const FOOTER: &'static [u8] = &[b'\'', b'\r', b'\n'];
+const HEADER: &[u8] = b"-ERR ";
+const FOOTER: &[u8] = b"'\r\n";
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Payload {
-    // Unknown error
-    Unknown(String),
+    // Unknown error, the text as sent, not necessarily utf8
+    Unknown(Bytes),
     // Unknown protocol error
     UnknownProtocolOperation,
     // Client attempted to connect to a route port instead of the client port
@@ -58,18 +58,34 @@ pub enum Payload {
     // Client sent a malformed subject (e.g. sub foo. 90)
     InvalidSubject,
     // The user specified in the message does not have permission to subscribe to the subject.
-    PermissionsViolationForSubscription(String),
+    PermissionsViolationForSubscription(Bytes),
     // The user specified in the message does not have permission to publish to the subject.
-    PermissionsViolationForPublishTo(String),
+    PermissionsViolationForPublishTo(Bytes),
 }
 
 impl Payload {
     pub fn encode(&self, dst: &mut bytes::BytesMut) -> Result<(), io::Error> {
+        let (prefix, msg) = self.parts();
+
         dst.put_slice(HEADER);
         dst.put_u8(b'\'');
+        dst.put_slice(prefix);
+        dst.put_slice(msg);
+        dst.put_slice(FOOTER);
+
+        Ok(())
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        let (prefix, msg) = self.parts();
+
+        HEADER.len() + 1 + prefix.len() + msg.len() + FOOTER.len()
+    }
 
+    // known prefix and the variable part of the error text
+    pub(super) fn parts(&self) -> (&[u8], &[u8]) {
         let msg = match self {
-            Payload::Unknown(s) => s.as_bytes(),
+            Payload::Unknown(s) => s,
             Payload::UnknownProtocolOperation => UNKNOWN_PROTOCOL_OPERATION,
             Payload::AttemptedToConnectToRoutePort => ATTEMPTED_TO_CONNECT_TO_ROUTE_PORT,
             Payload::AuthorizationViolation => AUTHORIZATION_VIOLATION,
@@ -84,31 +100,26 @@ impl Payload {
             Payload::MaximumPayloadViolation => MAXIMUM_PAYLOAD_VIOLATION,
             Payload::InvalidSubject => INVALID_SUBJECT,
             Payload::PermissionsViolationForSubscription(s) => {
-                dst.put_slice(PERMISSIONS_VIOLATION_FOR_SUBSCRIPTION);
-                dst.put_slice(s.as_bytes());
-                dst.put_slice(FOOTER);
-
-                return Ok(());
+                return (PERMISSIONS_VIOLATION_FOR_SUBSCRIPTION, s);
             }
             Payload::PermissionsViolationForPublishTo(s) => {
-                dst.put_slice(PERMISSIONS_VIOLATION_FOR_PUBLISH_TO);
-                dst.put_slice(s.as_bytes());
-                dst.put_slice(FOOTER);
-
-                return Ok(());
+                return (PERMISSIONS_VIOLATION_FOR_PUBLISH_TO, s);
             }
         };
 
-        dst.put_slice(msg);
-        dst.put_slice(FOOTER);
+        (&[], msg)
+    }
+}
 
-        Ok(())
+impl From<&str> for Payload {
+    fn from(raw: &str) -> Self {
+        Self::from(Bytes::copy_from_slice(raw.as_bytes()))
     }
 }
 
-impl From<&[u8]> for Payload {
-    fn from(raw: &[u8]) -> Self {
-        match raw {
+impl From<Bytes> for Payload {
+    fn from(raw: Bytes) -> Self {
+        match &raw[..] {
             s if s.starts_with(UNKNOWN_PROTOCOL_OPERATION) => Self::UnknownProtocolOperation,
             s if s.starts_with(ATTEMPTED_TO_CONNECT_TO_ROUTE_PORT) => {
                 Self::AttemptedToConnectToRoutePort
@@ -127,22 +138,14 @@ impl From<&[u8]> for Payload {
             s if s.starts_with(PERMISSIONS_VIOLATION_FOR_SUBSCRIPTION) => {
                 let plen = PERMISSIONS_VIOLATION_FOR_SUBSCRIPTION.len();
 
-                Self::PermissionsViolationForSubscription(
-                    String::from_utf8(raw[plen..].to_vec())
-                        .unwrap_or_else(|_| "non utf8".to_string()),
-                )
+                Self::PermissionsViolationForSubscription(raw.slice(plen..))
             }
             s if s.starts_with(PERMISSIONS_VIOLATION_FOR_PUBLISH_TO) => {
                 let plen = PERMISSIONS_VIOLATION_FOR_PUBLISH_TO.len();
 
-                Self::PermissionsViolationForPublishTo(
-                    String::from_utf8(raw[plen..].to_vec())
-                        .unwrap_or_else(|_| "non utf8".to_string()),
-                )
+                Self::PermissionsViolationForPublishTo(raw.slice(plen..))
             }
-            s @ _ => Self::Unknown(
-                String::from_utf8(s.to_vec()).unwrap_or_else(|_| "non utf8".to_string()),
-            ),
+            _ => Self::Unknown(raw),
         }
     }
 }
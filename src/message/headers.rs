@@ -0,0 +1,273 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::error::ProtocolError;
+
+const VERSION: &[u8] = b"NATS/1.0";
+const CLRF: &[u8] = b"\r\n";
+
+/// HPUB/HMSG header block, entries are kept in insertion order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Headers {
+    // inline status, `NATS/1.0 503` or `NATS/1.0 408 Request Timeout`
+    pub status: Option<u16>,
+    pub description: Option<String>,
+    entries: Vec<(String, String)>,
+}
+
+// NATS/1.0[ <status>[ <description>]]\r\n[<name>: <value>\r\n]*\r\n
+
+impl Headers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an entry, keeping the existing values of `name`.
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        self.entries.push((name.into(), value.into()));
+
+        self
+    }
+
+    /// Sets `name` to `value`, dropping its previous values.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        let name = name.into();
+
+        self.entries.retain(|(n, _)| *n != name);
+        self.entries.push((name, value.into()));
+
+        self
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.entries.retain(|(n, _)| n != name);
+    }
+
+    /// First value of `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.entries
+            .iter()
+            .filter(move |(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Fails for names that are empty or contain whitespace or `:`, and for
+    /// values or a description spanning lines, they'd inject headers.
+    pub fn encode(&self, dst: &mut BytesMut) -> Result<(), ProtocolError> {
+        self.validate()?;
+
+        dst.put_slice(VERSION);
+
+        if let Some(status) = self.status {
+            dst.put_slice(&b" "[..]);
+            super::put_usize(dst, status as usize);
+
+            if let Some(description) = &self.description {
+                dst.put_slice(&b" "[..]);
+                dst.put_slice(description.as_bytes());
+            }
+        }
+        dst.put_slice(CLRF);
+
+        for (name, value) in &self.entries {
+            dst.put_slice(name.as_bytes());
+            dst.put_slice(&b": "[..]);
+            dst.put_slice(value.as_bytes());
+            dst.put_slice(CLRF);
+        }
+        dst.put_slice(CLRF);
+
+        Ok(())
+    }
+
+    /// Exact number of bytes `encode` writes, the header size of HPUB/HMSG.
+    pub fn encoded_len(&self) -> usize {
+        let status = match self.status {
+            Some(status) => {
+                1 + super::usize_len(status as usize)
+                    + self.description.as_ref().map_or(0, |d| 1 + d.len())
+            }
+            None => 0,
+        };
+
+        let entries: usize = self
+            .entries
+            .iter()
+            .map(|(n, v)| n.len() + 2 + v.len() + CLRF.len())
+            .sum();
+
+        VERSION.len() + status + CLRF.len() + entries + CLRF.len()
+    }
+
+    pub fn to_bytes(&self) -> Result<Bytes, ProtocolError> {
+        let mut dst = BytesMut::with_capacity(self.encoded_len());
+        self.encode(&mut dst)?;
+
+        Ok(dst.freeze())
+    }
+
+    fn validate(&self) -> Result<(), ProtocolError> {
+        let multiline = |s: &str| s.contains(['\r', '\n']);
+
+        if let Some(description) = self.description.as_deref().filter(|d| multiline(d)) {
+            return Err(ProtocolError::InvalidHeader(description.to_string()));
+        }
+
+        for (name, value) in &self.entries {
+            let invalid_name = name.is_empty()
+                || name.contains(|c: char| c == ':' || c.is_ascii_whitespace() || c.is_control());
+
+            if invalid_name || multiline(value) {
+                return Err(ProtocolError::InvalidHeader(name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses a raw header block as found in `hpublish::Payload::headers`.
+    pub fn decode(input: &[u8]) -> Result<Self, ProtocolError> {
+        let invalid = || ProtocolError::InvalidHeaders(Bytes::copy_from_slice(input));
+        let text = std::str::from_utf8(input).map_err(|_| invalid())?;
+
+        // the block ends with an empty line
+        let text = text.strip_suffix("\r\n\r\n").ok_or_else(invalid)?;
+        let mut lines = text.split("\r\n");
+
+        let mut headers = Self::new();
+
+        let version = lines.next().unwrap_or_default();
+        let status = version.strip_prefix("NATS/1.0").ok_or_else(invalid)?;
+
+        if !status.is_empty() {
+            let status = status.strip_prefix(' ').ok_or_else(invalid)?.trim_start();
+            let (code, description) = match status.split_once(' ') {
+                Some((code, description)) => (code, Some(description.trim())),
+                None => (status, None),
+            };
+
+            if code.len() != 3 || !code.bytes().all(|c| c.is_ascii_digit()) {
+                return Err(invalid());
+            }
+
+            headers.status = code.parse().ok();
+            headers.description = description
+                .filter(|d| !d.is_empty())
+                .map(ToString::to_string);
+        }
+
+        for line in lines {
+            let (name, value) = line.split_once(':').ok_or_else(invalid)?;
+
+            if name.is_empty() || name.contains(|c: char| c.is_ascii_whitespace()) {
+                return Err(invalid());
+            }
+
+            headers.append(name, value.trim());
+        }
+
+        Ok(headers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Headers, ProtocolError};
+
+    #[test]
+    fn test_encode() {
+        let mut headers = Headers::new();
+        headers
+            .append("Foo", "bar")
+            .append("Foo", "baz")
+            .insert("Nats-Msg-Id", "1");
+
+        let encoded = headers.to_bytes().expect("valid");
+
+        assert_eq!(
+            &b"NATS/1.0\r\nFoo: bar\r\nFoo: baz\r\nNats-Msg-Id: 1\r\n\r\n"[..],
+            &encoded[..]
+        );
+        assert_eq!(encoded.len(), headers.encoded_len());
+        assert_eq!(Ok(headers), Headers::decode(&encoded));
+
+        assert_eq!(
+            &b"NATS/1.0\r\n\r\n"[..],
+            &Headers::new().to_bytes().expect("valid")[..]
+        );
+    }
+
+    #[test]
+    fn test_encode_injection() {
+        for (name, value) in [
+            ("Foo", "bar\r\nNats-Rollup: all"),
+            ("Foo", "bar\n"),
+            ("Foo: bar\r\nBaz", "1"),
+            ("Foo:", "1"),
+            ("Foo Bar", "1"),
+            ("", "1"),
+        ] {
+            let mut headers = Headers::new();
+            headers.insert(name, value);
+
+            assert_eq!(
+                Err(ProtocolError::InvalidHeader(name.to_string())),
+                headers.to_bytes()
+            );
+        }
+
+        let mut headers = Headers::new();
+        headers.status = Some(408);
+        headers.description = Some("Timeout\r\nFoo: bar".into());
+
+        assert!(headers.to_bytes().is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        let headers = Headers::decode(b"NATS/1.0 408 Request Timeout\r\nA:1\r\nB:  two \r\n\r\n")
+            .expect("headers");
+
+        assert_eq!(Some(408), headers.status);
+        assert_eq!(Some("Request Timeout"), headers.description.as_deref());
+        assert_eq!(Some("1"), headers.get("A"));
+        assert_eq!(Some("two"), headers.get("B"));
+        assert_eq!(None, headers.get("C"));
+
+        let headers = Headers::decode(b"NATS/1.0 503\r\n\r\n").expect("headers");
+
+        assert_eq!(Some(503), headers.status);
+        assert_eq!(None, headers.description);
+        assert!(headers.is_empty());
+
+        for input in [
+            &b"NATS/1.0\r\nA: 1\r\n"[..],
+            b"NATS/2.0\r\n\r\n",
+            b"NATS/1.0 40\r\n\r\n",
+            b"NATS/1.0\r\nA 1\r\n\r\n",
+        ] {
+            assert!(matches!(
+                Headers::decode(input),
+                Err(ProtocolError::InvalidHeaders(_))
+            ));
+        }
+    }
+}
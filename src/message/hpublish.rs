@@ -0,0 +1,77 @@
+use std::io;
+
+use bytes::{BufMut, Bytes};
+
+use super::{put_usize, usize_len};
+
+const HEADER: &[u8] = b"HPUB ";
+const CLRF: &[u8] = b"\r\n";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Payload {
+    pub subject: Bytes,
+    pub reply_to: Option<Bytes>,
+    pub header_size: usize,
+    pub total_size: usize,
+    // raw header block, `NATS/1.0` line and the empty line included
+    pub headers: Option<Bytes>,
+    pub payload: Option<Bytes>,
+}
+
+// HPUB <subject> [reply-to] <#header bytes> <#total bytes>\r\n[headers]\r\n\r\n[payload]
+
+impl Payload {
+    pub fn encode(&self, dst: &mut bytes::BytesMut) -> Result<(), io::Error> {
+        self.encode_head(dst)?;
+
+        if let Some(payload) = &self.payload {
+            dst.put_slice(payload);
+        }
+        dst.put_slice(CLRF);
+
+        Ok(())
+    }
+
+    // control line and headers, the payload and trailing CLRF are up to the caller
+    pub fn encode_head(&self, dst: &mut bytes::BytesMut) -> Result<(), io::Error> {
+        dst.put_slice(HEADER);
+        dst.put_slice(&self.subject);
+
+        if let Some(reply_to) = &self.reply_to {
+            dst.put_slice(&b" "[..]);
+            dst.put_slice(reply_to);
+        };
+
+        dst.put_slice(&b" "[..]);
+        put_usize(dst, self.header_size);
+
+        dst.put_slice(&b" "[..]);
+        put_usize(dst, self.total_size);
+
+        dst.put_slice(CLRF);
+
+        if let Some(headers) = &self.headers {
+            dst.put_slice(headers);
+        }
+
+        Ok(())
+    }
+
+    pub fn encoded_len(&self) -> usize {
+        let reply_to = self.reply_to.as_ref().map_or(0, |r| 1 + r.len());
+        let headers = self.headers.as_ref().map_or(0, |h| h.len());
+        let payload = self.payload.as_ref().map_or(0, |p| p.len());
+
+        HEADER.len()
+            + self.subject.len()
+            + reply_to
+            + 1
+            + usize_len(self.header_size)
+            + 1
+            + usize_len(self.total_size)
+            + CLRF.len()
+            + headers
+            + payload
+            + CLRF.len()
+    }
+}
@@ -3,8 +3,8 @@ use std::io;
 use bytes::BufMut;
 use serde::{Deserialize, Serialize};
 
-const HEADER: &'static [u8] = &[b'I', b'N', b'F', b'O', b' '];
-const FOOTER: &'static [u8] = &[b'\r', b'\n'];
+pub(crate) const HEADER: &[u8] = b"INFO ";
+const FOOTER: &[u8] = b"\r\n";
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Payload {
@@ -52,16 +52,17 @@ impl Payload {
     pub fn encode(&self, dst: &mut bytes::BytesMut) -> Result<(), io::Error> {
         dst.put_slice(HEADER);
 
-        serde_json::to_writer(dst.writer(), self).map_err(|_| {
-            let kind = io::ErrorKind::Other;
-
-            io::Error::new(kind, "cannot encode json")
-        })?;
+        serde_json::to_writer(dst.writer(), self)
+            .map_err(|_| io::Error::other("cannot encode json"))?;
 
         dst.put_slice(FOOTER);
 
         Ok(())
     }
+
+    pub fn encoded_len(&self) -> usize {
+        HEADER.len() + super::json_len(self) + FOOTER.len()
+    }
 }
 
 #[test]
@@ -0,0 +1,71 @@
+use std::io;
+use std::marker::PhantomData;
+
+use bytes::{BufMut, Bytes};
+use serde::de::DeserializeOwned;
+
+const CLRF: &[u8] = b"\r\n";
+
+/// Raw JSON body of an INFO/CONNECT message, deserialized on demand.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Json<T> {
+    raw: Bytes,
+    payload: PhantomData<T>,
+}
+
+impl<T> Json<T> {
+    pub fn new(raw: Bytes) -> Self {
+        Self {
+            raw,
+            payload: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn raw(&self) -> &Bytes {
+        &self.raw
+    }
+
+    #[inline]
+    pub fn into_raw(self) -> Bytes {
+        self.raw
+    }
+
+    pub(crate) fn encode(&self, header: &[u8], dst: &mut bytes::BytesMut) -> Result<(), io::Error> {
+        dst.put_slice(header);
+        dst.put_slice(&self.raw);
+        dst.put_slice(CLRF);
+
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> Json<T> {
+    pub fn decode(&self) -> Result<T, io::Error> {
+        serde_json::from_slice(&self.raw).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::Json;
+    use crate::message::info::Payload;
+
+    #[test]
+    fn test_decode() {
+        let json = Json::<Payload>::new(Bytes::from(
+            "{\"server_id\":\"id\",\"version\":\"2.9.0\",\"go\":\"go1.19\",\"host\":\"0.0.0.0\",\"port\":4222}",
+        ));
+
+        let payload = json.decode().expect("ok");
+
+        assert_eq!("id", payload.server_id);
+        assert_eq!(4222, payload.port);
+
+        let json = Json::<Payload>::new(Bytes::from("{\"unexpected\":true}"));
+
+        assert!(json.decode().is_err());
+    }
+}
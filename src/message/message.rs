@@ -2,8 +2,10 @@ use std::io;
 
 use bytes::{BufMut, Bytes};
 
-const HEADER: &'static [u8] = &[b'M', b'S', b'G', b' '];
-const CLRF: &'static [u8] = &[b'\r', b'\n'];
+use super::{put_usize, usize_len};
+
+const HEADER: &[u8] = b"MSG ";
+const CLRF: &[u8] = b"\r\n";
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Payload {
@@ -18,11 +20,23 @@ pub struct Payload {
 
 impl Payload {
     pub fn encode(&self, dst: &mut bytes::BytesMut) -> Result<(), io::Error> {
+        self.encode_head(dst)?;
+
+        if let Some(payload) = &self.payload {
+            dst.put_slice(payload);
+        }
+        dst.put_slice(CLRF);
+
+        Ok(())
+    }
+
+    // control line only, the payload and trailing CLRF are up to the caller
+    pub fn encode_head(&self, dst: &mut bytes::BytesMut) -> Result<(), io::Error> {
         dst.put_slice(HEADER);
         dst.put_slice(&self.subject);
 
         dst.put_slice(&b" "[..]);
-        dst.put_slice(&self.sid.to_string().as_bytes());
+        put_usize(dst, self.sid);
 
         if let Some(reply_to) = &self.reply_to {
             dst.put_slice(&b" "[..]);
@@ -30,15 +44,26 @@ impl Payload {
         };
 
         dst.put_slice(&b" "[..]);
-        dst.put_slice(&self.payload_size.to_string().as_bytes());
+        put_usize(dst, self.payload_size);
 
         dst.put_slice(CLRF);
 
-        if let Some(payload) = &self.payload {
-            dst.put_slice(payload);
-        }
-        dst.put_slice(CLRF);
-
         Ok(())
     }
+
+    pub fn encoded_len(&self) -> usize {
+        let reply_to = self.reply_to.as_ref().map_or(0, |r| 1 + r.len());
+        let payload = self.payload.as_ref().map_or(0, |p| p.len());
+
+        HEADER.len()
+            + self.subject.len()
+            + 1
+            + usize_len(self.sid)
+            + reply_to
+            + 1
+            + usize_len(self.payload_size)
+            + CLRF.len()
+            + payload
+            + CLRF.len()
+    }
 }
@@ -2,12 +2,25 @@ use std::io;
 
 use bytes::{BufMut, Bytes};
 
+use crate::error::ProtocolError;
+
+pub use headers::Headers;
+pub use trace::Trace;
+
 pub mod connect;
+pub mod headers;
+pub mod hmessage;
+pub mod hpublish;
 pub mod info;
+pub mod json;
+#[allow(clippy::module_inception)]
 pub mod message;
 pub mod op;
 pub mod publish;
+#[cfg(feature = "serde")]
+pub(crate) mod repr;
 pub mod subscribe;
+mod trace;
 pub mod unsubscribe;
 pub mod error;
 
@@ -19,10 +32,18 @@ pub enum Message {
     Pong,
     Info(info::Payload),
     Connect(connect::Payload),
+    // INFO/CONNECT kept as raw json, see `Codec::with_lazy_json`
+    RawInfo(json::Json<info::Payload>),
+    RawConnect(json::Json<connect::Payload>),
     Message(message::Payload),
     Publish(publish::Payload),
+    HMessage(hmessage::Payload),
+    HPublish(hpublish::Payload),
     Subscribe(subscribe::Payload),
     Unsubscribe(unsubscribe::Payload),
+    // bad frame skipped by a recovering `Codec`, `raw` is its control line as
+    // received, a payload not matching the declared size is dropped
+    Invalid { raw: Bytes, reason: ProtocolError },
 }
 
 impl Message {
@@ -40,20 +61,281 @@ impl Message {
             }
             Self::Info(p) => p.encode(dst)?,
             Self::Connect(p) => p.encode(dst)?,
+            Self::RawInfo(p) => p.encode(info::HEADER, dst)?,
+            Self::RawConnect(p) => p.encode(connect::HEADER, dst)?,
             Self::Message(p) => p.encode(dst)?,
             Self::Publish(p) => p.encode(dst)?,
+            Self::HMessage(p) => p.encode(dst)?,
+            Self::HPublish(p) => p.encode(dst)?,
             Self::Subscribe(p) => p.encode(dst)?,
             Self::Unsubscribe(p) => p.encode(dst)?,
+            // never sent on, the raw line may be anything
+            Self::Invalid { .. } => return Err(ProtocolError::InvalidFrame.into()),
         };
 
         Ok(())
     }
+
+    /// Exact number of bytes `encode` writes for this message.
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            Self::Ok => 5,
+            Self::Err(p) => p.encoded_len(),
+            Self::Ping | Self::Pong => 6,
+            Self::Info(p) => p.encoded_len(),
+            Self::Connect(p) => p.encoded_len(),
+            Self::RawInfo(p) => info::HEADER.len() + p.raw().len() + 2,
+            Self::RawConnect(p) => connect::HEADER.len() + p.raw().len() + 2,
+            Self::Message(p) => p.encoded_len(),
+            Self::Publish(p) => p.encoded_len(),
+            Self::HMessage(p) => p.encoded_len(),
+            Self::HPublish(p) => p.encoded_len(),
+            Self::Subscribe(p) => p.encoded_len(),
+            Self::Unsubscribe(p) => p.encoded_len(),
+            Self::Invalid { raw, .. } => raw.len(),
+        }
+    }
+}
+
+impl Message {
+    /// Checks the invariants `encode` relies on: well formed subjects, queue
+    /// groups, header blocks and single line text, declared sizes matching the
+    /// body.
+    pub fn validate(&self) -> Result<(), ProtocolError> {
+        match self {
+            Self::Ok | Self::Ping | Self::Pong | Self::Info(_) | Self::Connect(_) => Ok(()),
+            Self::Err(p) => {
+                // sent quoted on a single line, the parser takes the last
+                // quote as the closing one so quotes in the text are fine
+                let (_, msg) = p.parts();
+
+                match msg.iter().any(|c| matches!(c, b'\r' | b'\n')) {
+                    true => Err(ProtocolError::InvalidErrorText(
+                        String::from_utf8_lossy(msg).into_owned(),
+                    )),
+                    false => Ok(()),
+                }
+            }
+            Self::RawInfo(p) => validate_json(p.raw()),
+            Self::RawConnect(p) => validate_json(p.raw()),
+            Self::Message(p) => {
+                validate_subject(&p.subject)?;
+                validate_reply_to(&p.reply_to)?;
+                validate_size(p.payload_size, &p.payload)
+            }
+            Self::Publish(p) => {
+                validate_subject(&p.subject)?;
+                validate_reply_to(&p.reply_to)?;
+                validate_size(p.payload_size, &p.payload)
+            }
+            Self::HMessage(p) => {
+                validate_subject(&p.subject)?;
+                validate_reply_to(&p.reply_to)?;
+                validate_headers(&p.headers)?;
+                validate_sizes(p.header_size, p.total_size, &p.headers, &p.payload)
+            }
+            Self::HPublish(p) => {
+                validate_subject(&p.subject)?;
+                validate_reply_to(&p.reply_to)?;
+                validate_headers(&p.headers)?;
+                validate_sizes(p.header_size, p.total_size, &p.headers, &p.payload)
+            }
+            Self::Subscribe(p) => {
+                validate_subject(&p.subject)?;
+
+                match &p.queue_group {
+                    Some(q) if !is_token(q) => Err(ProtocolError::InvalidQueueGroup(q.clone())),
+                    _ => Ok(()),
+                }
+            }
+            // sid and max_msgs only
+            Self::Unsubscribe(_) => Ok(()),
+            Self::Invalid { .. } => Err(ProtocolError::InvalidFrame),
+        }
+    }
+
+    /// Sets the declared sizes from the body and headers.
+    pub fn derive_sizes(&mut self) {
+        let len = |b: &Option<Bytes>| b.as_ref().map_or(0, |b| b.len());
+
+        match self {
+            Self::Message(p) => p.payload_size = len(&p.payload),
+            Self::Publish(p) => p.payload_size = len(&p.payload),
+            Self::HMessage(p) => {
+                p.header_size = len(&p.headers);
+                p.total_size = p.header_size + len(&p.payload);
+            }
+            Self::HPublish(p) => {
+                p.header_size = len(&p.headers);
+                p.total_size = p.header_size + len(&p.payload);
+            }
+            _ => {}
+        }
+    }
+
+    /// Encodes the message without its body and trailing CLRF, bodyless
+    /// messages are encoded as a whole.
+    pub fn encode_head(&self, dst: &mut bytes::BytesMut) -> Result<(), io::Error> {
+        match self {
+            Self::Message(p) => p.encode_head(dst),
+            Self::Publish(p) => p.encode_head(dst),
+            Self::HMessage(p) => p.encode_head(dst),
+            Self::HPublish(p) => p.encode_head(dst),
+            _ => self.encode(dst),
+        }
+    }
 }
 
 impl Message {
+    /// `PUB`, the size is taken from `body`.
+    pub fn publish(subject: impl Into<Bytes>, body: impl Into<Bytes>) -> Self {
+        let body = body.into();
+
+        Self::Publish(publish::Payload {
+            subject: subject.into(),
+            reply_to: None,
+            payload_size: body.len(),
+            payload: Some(body),
+        })
+    }
+
+    pub fn publish_with_reply(
+        subject: impl Into<Bytes>,
+        reply_to: impl Into<Bytes>,
+        body: impl Into<Bytes>,
+    ) -> Self {
+        let body = body.into();
+
+        Self::Publish(publish::Payload {
+            subject: subject.into(),
+            reply_to: Some(reply_to.into()),
+            payload_size: body.len(),
+            payload: Some(body),
+        })
+    }
+
+    /// `HPUB`, header and total sizes are taken from `headers` and `body`.
+    pub fn hpublish(
+        subject: impl Into<Bytes>,
+        headers: &Headers,
+        body: impl Into<Bytes>,
+    ) -> Result<Self, ProtocolError> {
+        let mut message = Self::publish(subject, body);
+        message.set_headers(headers)?;
+
+        Ok(message)
+    }
+
+    pub fn hpublish_with_reply(
+        subject: impl Into<Bytes>,
+        reply_to: impl Into<Bytes>,
+        headers: &Headers,
+        body: impl Into<Bytes>,
+    ) -> Result<Self, ProtocolError> {
+        let mut message = Self::publish_with_reply(subject, reply_to, body);
+        message.set_headers(headers)?;
+
+        Ok(message)
+    }
+
+    pub fn subscribe(subject: impl Into<Bytes>, sid: usize) -> Self {
+        Self::Subscribe(subscribe::Payload {
+            subject: subject.into(),
+            sid,
+            queue_group: None,
+        })
+    }
+
+    pub fn queue_subscribe(
+        subject: impl Into<Bytes>,
+        queue_group: impl Into<Bytes>,
+        sid: usize,
+    ) -> Self {
+        Self::Subscribe(subscribe::Payload {
+            subject: subject.into(),
+            sid,
+            queue_group: Some(queue_group.into()),
+        })
+    }
+
+    pub fn unsubscribe(sid: usize) -> Self {
+        Self::Unsubscribe(unsubscribe::Payload {
+            sid,
+            max_messages: None,
+        })
+    }
+
+    /// `UNSUB` once `max_messages` more messages have been delivered.
+    pub fn unsubscribe_after(sid: usize, max_messages: usize) -> Self {
+        Self::Unsubscribe(unsubscribe::Payload {
+            sid,
+            max_messages: Some(max_messages),
+        })
+    }
+
+    /// Replaces the header block, turning `PUB`/`MSG` into `HPUB`/`HMSG`.
+    /// Sizes are updated to match.
+    pub fn set_headers(&mut self, headers: &Headers) -> Result<(), ProtocolError> {
+        let headers = Some(headers.to_bytes()?);
+
+        match self {
+            Self::Publish(p) => {
+                *self = Self::HPublish(hpublish::Payload {
+                    subject: std::mem::take(&mut p.subject),
+                    reply_to: p.reply_to.take(),
+                    headers,
+                    payload: p.payload.take(),
+                    ..Default::default()
+                })
+            }
+            Self::Message(p) => {
+                *self = Self::HMessage(hmessage::Payload {
+                    subject: std::mem::take(&mut p.subject),
+                    sid: p.sid,
+                    reply_to: p.reply_to.take(),
+                    headers,
+                    payload: p.payload.take(),
+                    ..Default::default()
+                })
+            }
+            Self::HPublish(p) => p.headers = headers,
+            Self::HMessage(p) => p.headers = headers,
+            _ => return Err(ProtocolError::UnexpectedHeaders),
+        }
+
+        self.derive_sizes();
+
+        Ok(())
+    }
+}
+
+impl Message {
+    /// `None` for `Message::Invalid`.
+    #[inline]
+    pub fn op(&self) -> Option<op::Op> {
+        Some(match self {
+            Self::Ok => op::Op::Ok,
+            Self::Err(_) => op::Op::Err,
+            Self::Ping => op::Op::Ping,
+            Self::Pong => op::Op::Pong,
+            Self::Info(_) | Self::RawInfo(_) => op::Op::Info,
+            Self::Connect(_) | Self::RawConnect(_) => op::Op::Connect,
+            Self::Message(_) => op::Op::Message,
+            Self::Publish(_) => op::Op::Publish,
+            Self::HMessage(_) => op::Op::HMessage,
+            Self::HPublish(_) => op::Op::HPublish,
+            Self::Subscribe(_) => op::Op::Subscribe,
+            Self::Unsubscribe(_) => op::Op::Unsubscribe,
+            Self::Invalid { .. } => return None,
+        })
+    }
+
     #[inline]
     pub fn with_body(&self) -> bool {
-        matches!(self, Self::Message(_) | Self::Publish(_))
+        matches!(
+            self,
+            Self::Message(_) | Self::Publish(_) | Self::HMessage(_) | Self::HPublish(_)
+        )
     }
 
     #[inline]
@@ -61,16 +343,481 @@ impl Message {
         Some(match self {
             Message::Message(m) => m.payload_size,
             Message::Publish(m) => m.payload_size,
+            Message::HMessage(m) => m.total_size,
+            Message::HPublish(m) => m.total_size,
             _ => return None,
         })
     }
 
     #[inline]
-    pub fn set_body(&mut self, body: Bytes) {
+    pub fn subject(&self) -> Option<&Bytes> {
+        Some(match self {
+            Message::Message(m) => &m.subject,
+            Message::Publish(m) => &m.subject,
+            Message::HMessage(m) => &m.subject,
+            Message::HPublish(m) => &m.subject,
+            Message::Subscribe(m) => &m.subject,
+            _ => return None,
+        })
+    }
+
+    #[inline]
+    pub fn reply_to(&self) -> Option<&Bytes> {
+        match self {
+            Message::Message(m) => m.reply_to.as_ref(),
+            Message::Publish(m) => m.reply_to.as_ref(),
+            Message::HMessage(m) => m.reply_to.as_ref(),
+            Message::HPublish(m) => m.reply_to.as_ref(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn sid(&self) -> Option<usize> {
+        Some(match self {
+            Message::Message(m) => m.sid,
+            Message::HMessage(m) => m.sid,
+            Message::Subscribe(m) => m.sid,
+            Message::Unsubscribe(m) => m.sid,
+            _ => return None,
+        })
+    }
+
+    /// Raw HPUB/HMSG header block.
+    #[inline]
+    pub fn header_block(&self) -> Option<&Bytes> {
+        match self {
+            Message::HMessage(m) => m.headers.as_ref(),
+            Message::HPublish(m) => m.headers.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Parsed header block, empty for messages without one.
+    pub fn headers(&self) -> Result<Headers, ProtocolError> {
+        match self.header_block() {
+            Some(block) => Headers::decode(block),
+            None => Ok(Headers::new()),
+        }
+    }
+
+    #[inline]
+    pub fn body(&self) -> Option<&Bytes> {
+        match self {
+            Message::Message(m) => m.payload.as_ref(),
+            Message::Publish(m) => m.payload.as_ref(),
+            Message::HMessage(m) => m.payload.as_ref(),
+            Message::HPublish(m) => m.payload.as_ref(),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn set_body(&mut self, mut body: Bytes) {
         match self {
             Message::Message(m) => m.payload = Some(body),
             Message::Publish(m) => m.payload = Some(body),
+            Message::HMessage(m) => {
+                m.headers = Some(body.split_to(m.header_size));
+                m.payload = Some(body);
+            }
+            Message::HPublish(m) => {
+                m.headers = Some(body.split_to(m.header_size));
+                m.payload = Some(body);
+            }
             _ => unreachable!("bodyless"),
         }
     }
 }
+
+// non-empty and free of whitespace, so it stays a single control line field
+#[inline]
+fn is_token(s: &[u8]) -> bool {
+    !s.is_empty() && !s.iter().any(|c| matches!(c, b' ' | b'\t' | b'\r' | b'\n'))
+}
+
+#[inline]
+fn validate_subject(subject: &Bytes) -> Result<(), ProtocolError> {
+    match is_token(subject) {
+        true => Ok(()),
+        false => Err(ProtocolError::InvalidSubject(subject.clone())),
+    }
+}
+
+#[inline]
+fn validate_reply_to(reply_to: &Option<Bytes>) -> Result<(), ProtocolError> {
+    match reply_to {
+        Some(r) if !is_token(r) => Err(ProtocolError::InvalidReplyTo(r.clone())),
+        _ => Ok(()),
+    }
+}
+
+#[inline]
+fn validate_size(declared: usize, payload: &Option<Bytes>) -> Result<(), ProtocolError> {
+    let actual = payload.as_ref().map_or(0, |p| p.len());
+
+    match declared == actual {
+        true => Ok(()),
+        false => Err(ProtocolError::BodySizeMismatch { declared, actual }),
+    }
+}
+
+#[inline]
+fn validate_json(raw: &Bytes) -> Result<(), ProtocolError> {
+    match raw.iter().any(|c| matches!(c, b'\r' | b'\n')) {
+        true => Err(ProtocolError::InvalidJson(raw.clone())),
+        false => Ok(()),
+    }
+}
+
+// `NATS/1.0` line and the empty line closing the block
+#[inline]
+fn validate_headers(headers: &Option<Bytes>) -> Result<(), ProtocolError> {
+    let headers = headers.clone().unwrap_or_default();
+
+    match headers.starts_with(b"NATS/1.0") && headers.ends_with(b"\r\n\r\n") {
+        true => Ok(()),
+        false => Err(ProtocolError::InvalidHeaders(headers)),
+    }
+}
+
+#[inline]
+fn validate_sizes(
+    header_size: usize,
+    total_size: usize,
+    headers: &Option<Bytes>,
+    payload: &Option<Bytes>,
+) -> Result<(), ProtocolError> {
+    let actual = headers.as_ref().map_or(0, |h| h.len());
+
+    if header_size != actual {
+        return Err(ProtocolError::HeaderSizeMismatch {
+            declared: header_size,
+            actual,
+        });
+    }
+
+    let actual = actual + payload.as_ref().map_or(0, |p| p.len());
+
+    match total_size == actual {
+        true => Ok(()),
+        false => Err(ProtocolError::BodySizeMismatch {
+            declared: total_size,
+            actual,
+        }),
+    }
+}
+
+// formats `n` through a stack buffer instead of `to_string`
+#[inline]
+pub(crate) fn put_usize(dst: &mut bytes::BytesMut, mut n: usize) {
+    let mut buf = [0u8; 20];
+    let mut idx = buf.len();
+
+    loop {
+        idx -= 1;
+        buf[idx] = b'0' + (n % 10) as u8;
+        n /= 10;
+
+        if n == 0 {
+            break;
+        }
+    }
+
+    dst.put_slice(&buf[idx..]);
+}
+
+#[inline]
+pub(crate) fn usize_len(mut n: usize) -> usize {
+    let mut len = 1;
+
+    while n >= 10 {
+        n /= 10;
+        len += 1;
+    }
+
+    len
+}
+
+// length of the compact json `serde_json::to_writer` produces, 0 if `value`
+// can't be serialized, in which case encoding it fails too
+pub(crate) fn json_len<T: serde::Serialize>(value: &T) -> usize {
+    struct Counter(usize);
+
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+
+    match serde_json::to_writer(&mut counter, value) {
+        Ok(_) => counter.0,
+        Err(_) => 0,
+    }
+}
+
+#[test]
+fn test_put_usize() {
+    for n in [0, 7, 10, 99, 100, 4222, 1 << 20, usize::MAX] {
+        let mut dst = bytes::BytesMut::new();
+
+        put_usize(&mut dst, n);
+
+        assert_eq!(n.to_string().as_bytes(), &dst[..]);
+        assert_eq!(dst.len(), usize_len(n));
+    }
+}
+
+#[test]
+fn test_encoded_len() {
+    let messages = vec![
+        Message::Ok,
+        Message::Ping,
+        Message::Pong,
+        Message::Err(error::Payload::SlowConsumer),
+        Message::Err(error::Payload::PermissionsViolationForPublishTo("foo".into())),
+        Message::Err(error::Payload::Unknown("unknown".into())),
+        Message::Info(info::Payload::default()),
+        Message::Connect(connect::Payload::default()),
+        Message::RawInfo(json::Json::new(Bytes::from("{}"))),
+        Message::Publish(publish::Payload {
+            subject: Bytes::from("FOO"),
+            reply_to: Some(Bytes::from("INBOX.1")),
+            payload_size: 11,
+            payload: Some(Bytes::from("hello world")),
+        }),
+        Message::Publish(publish::Payload {
+            subject: Bytes::from("FOO"),
+            reply_to: None,
+            payload_size: 0,
+            payload: None,
+        }),
+        Message::Message(message::Payload {
+            subject: Bytes::from("FOO"),
+            sid: 12345,
+            reply_to: None,
+            payload_size: 2,
+            payload: Some(Bytes::from("hi")),
+        }),
+        Message::Subscribe(subscribe::Payload {
+            subject: Bytes::from("FOO"),
+            sid: 1,
+            queue_group: Some(Bytes::from("Q")),
+        }),
+        Message::Unsubscribe(unsubscribe::Payload {
+            sid: 10,
+            max_messages: Some(100),
+        }),
+        Message::HPublish(hpublish::Payload {
+            subject: Bytes::from("FOO"),
+            reply_to: Some(Bytes::from("INBOX.1")),
+            header_size: 22,
+            total_size: 27,
+            headers: Some(Bytes::from("NATS/1.0\r\nA: B\r\n\r\n")),
+            payload: Some(Bytes::from("hello")),
+        }),
+        Message::HMessage(hmessage::Payload {
+            subject: Bytes::from("FOO"),
+            sid: 7,
+            reply_to: None,
+            header_size: 12,
+            total_size: 12,
+            headers: Some(Bytes::from("NATS/1.0\r\n\r\n")),
+            payload: None,
+        }),
+    ];
+
+    for message in messages {
+        let mut dst = bytes::BytesMut::new();
+
+        message.encode(&mut dst).expect("ok");
+
+        assert_eq!(dst.len(), message.encoded_len(), "{:?}", message);
+    }
+}
+
+#[test]
+fn test_validate() {
+    let publish = |subject: &'static str, reply_to: Option<&'static str>, size, payload| {
+        Message::Publish(publish::Payload {
+            subject: Bytes::from(subject),
+            reply_to: reply_to.map(Bytes::from),
+            payload_size: size,
+            payload: Some(Bytes::from(payload)),
+        })
+    };
+
+    assert_eq!(Ok(()), publish("FOO", Some("INBOX.1"), 5, "hello").validate());
+
+    assert_eq!(
+        Err(ProtocolError::BodySizeMismatch {
+            declared: 3,
+            actual: 5
+        }),
+        publish("FOO", None, 3, "hello").validate()
+    );
+    assert_eq!(
+        Err(ProtocolError::InvalidSubject(Bytes::from("FOO BAR"))),
+        publish("FOO BAR", None, 5, "hello").validate()
+    );
+    assert_eq!(
+        Err(ProtocolError::InvalidSubject(Bytes::new())),
+        publish("", None, 5, "hello").validate()
+    );
+    assert_eq!(
+        Err(ProtocolError::InvalidReplyTo(Bytes::from("IN\r\nBOX"))),
+        publish("FOO", Some("IN\r\nBOX"), 5, "hello").validate()
+    );
+
+    let subscribe = Message::Subscribe(subscribe::Payload {
+        subject: Bytes::from("FOO"),
+        sid: 1,
+        queue_group: Some(Bytes::new()),
+    });
+
+    assert_eq!(
+        Err(ProtocolError::InvalidQueueGroup(Bytes::new())),
+        subscribe.validate()
+    );
+
+    let mut hpublish = Message::HPublish(hpublish::Payload {
+        subject: Bytes::from("FOO"),
+        reply_to: None,
+        header_size: 0,
+        total_size: 0,
+        headers: Some(Bytes::from("NATS/1.0\r\n\r\n")),
+        payload: Some(Bytes::from("hello")),
+    });
+
+    assert_eq!(
+        Err(ProtocolError::HeaderSizeMismatch {
+            declared: 0,
+            actual: 12
+        }),
+        hpublish.validate()
+    );
+
+    hpublish.derive_sizes();
+
+    assert_eq!(Ok(()), hpublish.validate());
+    assert_eq!(Some(17), hpublish.body_size());
+
+    let hpublish = Message::HPublish(hpublish::Payload {
+        subject: Bytes::from("FOO"),
+        reply_to: None,
+        header_size: 8,
+        total_size: 8,
+        headers: Some(Bytes::from("A: B\r\n\r\n")),
+        payload: None,
+    });
+
+    assert_eq!(
+        Err(ProtocolError::InvalidHeaders(Bytes::from("A: B\r\n\r\n"))),
+        hpublish.validate()
+    );
+
+    assert_eq!(
+        Ok(()),
+        Message::Err(error::Payload::Unknown("it's".into())).validate()
+    );
+    assert_eq!(
+        Err(ProtocolError::InvalidErrorText("a\r\nPING".into())),
+        Message::Err(error::Payload::Unknown("a\r\nPING".into())).validate()
+    );
+    assert!(
+        Message::Err(error::Payload::PermissionsViolationForPublishTo(
+            "foo\r\nPUB".into()
+        ))
+        .validate()
+        .is_err()
+    );
+    assert_eq!(
+        Ok(()),
+        Message::Err(error::Payload::SlowConsumer).validate()
+    );
+
+    let raw = Bytes::from("{}\r\nPUB FOO 0\r\n");
+
+    assert_eq!(
+        Err(ProtocolError::InvalidJson(raw.clone())),
+        Message::RawInfo(json::Json::new(raw)).validate()
+    );
+
+    let invalid = Message::Invalid {
+        raw: Bytes::from("FOO\r\n"),
+        reason: ProtocolError::InvalidControlLine,
+    };
+
+    assert_eq!(Err(ProtocolError::InvalidFrame), invalid.validate());
+    assert!(invalid.encode(&mut bytes::BytesMut::new()).is_err());
+}
+
+#[test]
+fn test_constructors() {
+    let message = Message::publish("FOO", "hello");
+
+    assert_eq!(Ok(()), message.validate());
+    assert_eq!(Some(&Bytes::from("FOO")), message.subject());
+    assert_eq!(Some(5), message.body_size());
+    assert_eq!(None, message.reply_to());
+
+    let mut dst = bytes::BytesMut::new();
+
+    Message::publish_with_reply("FOO", "INBOX.1", "hello")
+        .encode(&mut dst)
+        .unwrap();
+    Message::queue_subscribe("FOO", "workers", 1)
+        .encode(&mut dst)
+        .unwrap();
+    Message::unsubscribe_after(1, 5).encode(&mut dst).unwrap();
+
+    assert_eq!(
+        &b"PUB FOO INBOX.1 5\r\nhello\r\nSUB FOO workers 1\r\nUNSUB 1 5\r\n"[..],
+        &dst[..]
+    );
+
+    let mut headers = Headers::new();
+    headers.insert("Foo", "bar");
+
+    let message = Message::hpublish_with_reply("FOO", "INBOX.1", &headers, "hello").unwrap();
+
+    assert_eq!(Ok(()), message.validate());
+    assert_eq!(Some(&Bytes::from("INBOX.1")), message.reply_to());
+    assert_eq!(Some(&Bytes::from("hello")), message.body());
+    assert_eq!(Some(22 + 5), message.body_size());
+    assert_eq!(Ok(headers.clone()), message.headers());
+
+    let mut message = Message::Message(message::Payload {
+        subject: Bytes::from("FOO"),
+        sid: 9,
+        reply_to: None,
+        payload_size: 5,
+        payload: Some(Bytes::from("hello")),
+    });
+    message.set_headers(&headers).unwrap();
+
+    assert!(matches!(message, Message::HMessage(_)));
+    assert_eq!(Ok(()), message.validate());
+    assert_eq!(Some(9), message.sid());
+    assert_eq!(Some(&headers.to_bytes().unwrap()), message.header_block());
+
+    assert_eq!(
+        Err(ProtocolError::UnexpectedHeaders),
+        Message::Ping.set_headers(&headers)
+    );
+
+    headers.insert("Foo", "bar\r\nBaz: 1");
+
+    assert_eq!(
+        Err(ProtocolError::InvalidHeader("Foo".into())),
+        Message::hpublish("FOO", &headers, "hello")
+    );
+}
@@ -3,7 +3,7 @@ use std::convert::TryFrom;
 #[derive(Debug, PartialEq)]
 pub struct InvalidOp(pub String);
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Op {
     Ok,
     Err,
@@ -13,6 +13,8 @@ pub enum Op {
     Publish,
     Connect,
     Message,
+    HPublish,
+    HMessage,
     Subscribe,
     Unsubscribe,
 }
@@ -26,6 +28,8 @@ impl TryFrom<&[u8]> for Op {
             // hottest
             [b'p' | b'P', b'u' | b'U', b'b' | b'B'] => Op::Publish,
             [b'm' | b'M', b's' | b'S', b'g' | b'G'] => Op::Message,
+            [b'h' | b'H', b'p' | b'P', b'u' | b'U', b'b' | b'B'] => Op::HPublish,
+            [b'h' | b'H', b'm' | b'M', b's' | b'S', b'g' | b'G'] => Op::HMessage,
             //
             [b's' | b'S', b'u' | b'U', b'b' | b'B'] => Op::Subscribe,
             [b'i' | b'I', b'n' | b'N', b'f' | b'F', b'o' | b'O'] => Op::Info,
@@ -43,13 +47,54 @@ impl TryFrom<&[u8]> for Op {
     }
 }
 
+impl Op {
+    /// Number of ops, `index` is below it.
+    pub(crate) const COUNT: usize = 12;
+
+    /// Position of the op in per-op tables, independent of the variant order.
+    #[inline]
+    pub(crate) const fn index(self) -> usize {
+        match self {
+            Op::Ok => 0,
+            Op::Err => 1,
+            Op::Info => 2,
+            Op::Ping => 3,
+            Op::Pong => 4,
+            Op::Publish => 5,
+            Op::Connect => 6,
+            Op::Message => 7,
+            Op::HPublish => 8,
+            Op::HMessage => 9,
+            Op::Subscribe => 10,
+            Op::Unsubscribe => 11,
+        }
+    }
+
+    /// Protocol name, `PUB` for `Op::Publish`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Op::Ok => "+OK",
+            Op::Err => "-ERR",
+            Op::Info => "INFO",
+            Op::Ping => "PING",
+            Op::Pong => "PONG",
+            Op::Publish => "PUB",
+            Op::Connect => "CONNECT",
+            Op::Message => "MSG",
+            Op::HPublish => "HPUB",
+            Op::HMessage => "HMSG",
+            Op::Subscribe => "SUB",
+            Op::Unsubscribe => "UNSUB",
+        }
+    }
+}
+
 fn invalid_op(value: &[u8]) -> InvalidOp {
     let invalid_op = String::from_utf8_lossy(value).to_string();
 
     InvalidOp(invalid_op)
 }
 
-
 #[cfg(test)]
 #[test]
 fn test_op_try_from() {
@@ -71,6 +116,12 @@ fn test_op_try_from() {
     assert_eq!(Ok(Op::Publish), Op::try_from(&b"pub"[..]));
     assert_eq!(Ok(Op::Publish), Op::try_from(&b"PUB"[..]));
 
+    assert_eq!(Ok(Op::HPublish), Op::try_from(&b"hpub"[..]));
+    assert_eq!(Ok(Op::HPublish), Op::try_from(&b"HPUB"[..]));
+
+    assert_eq!(Ok(Op::HMessage), Op::try_from(&b"hmsg"[..]));
+    assert_eq!(Ok(Op::HMessage), Op::try_from(&b"HMSG"[..]));
+
     assert_eq!(Ok(Op::Connect), Op::try_from(&b"connect"[..]));
     assert_eq!(Ok(Op::Connect), Op::try_from(&b"CONNECT"[..]));
 
@@ -80,5 +131,8 @@ fn test_op_try_from() {
     assert_eq!(Ok(Op::Unsubscribe), Op::try_from(&b"unsub"[..]));
     assert_eq!(Ok(Op::Unsubscribe), Op::try_from(&b"UNSUB"[..]));
 
-    assert_eq!(Err(InvalidOp("invalid".into())), Op::try_from(&b"invalid"[..]));
+    assert_eq!(
+        Err(InvalidOp("invalid".into())),
+        Op::try_from(&b"invalid"[..])
+    );
 }
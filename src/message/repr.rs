@@ -0,0 +1,387 @@
+use ::bytes::Bytes;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::json::Json;
+use super::Message;
+use super::{connect, error, hmessage, hpublish, info, message, publish, subscribe, unsubscribe};
+use crate::error::ProtocolError;
+
+// Stable, tagged representation of `Message`, one object per message:
+//
+//   {"op":"PUB","subject":"FOO","reply_to":"INBOX.1","body":"hello"}
+//
+// Sizes aren't stored, they're derived from the body when deserializing.
+// Bytes are a plain string when valid UTF-8 and `{"base64": ..}` otherwise.
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "SCREAMING_SNAKE_CASE")]
+enum Repr {
+    Ok,
+    Err {
+        message: String,
+    },
+    Ping,
+    Pong,
+    Info(info::Payload),
+    Connect(connect::Payload),
+    RawInfo {
+        #[serde(with = "bytes")]
+        json: Bytes,
+    },
+    RawConnect {
+        #[serde(with = "bytes")]
+        json: Bytes,
+    },
+    #[serde(rename = "MSG")]
+    Message {
+        #[serde(with = "bytes")]
+        subject: Bytes,
+        sid: usize,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        reply_to: Option<Bytes>,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        body: Option<Bytes>,
+    },
+    #[serde(rename = "PUB")]
+    Publish {
+        #[serde(with = "bytes")]
+        subject: Bytes,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        reply_to: Option<Bytes>,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        body: Option<Bytes>,
+    },
+    #[serde(rename = "HMSG")]
+    HMessage {
+        #[serde(with = "bytes")]
+        subject: Bytes,
+        sid: usize,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        reply_to: Option<Bytes>,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        headers: Option<Bytes>,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        body: Option<Bytes>,
+    },
+    #[serde(rename = "HPUB")]
+    HPublish {
+        #[serde(with = "bytes")]
+        subject: Bytes,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        reply_to: Option<Bytes>,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        headers: Option<Bytes>,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        body: Option<Bytes>,
+    },
+    #[serde(rename = "SUB")]
+    Subscribe {
+        #[serde(with = "bytes")]
+        subject: Bytes,
+        sid: usize,
+        #[serde(with = "option", default, skip_serializing_if = "Option::is_none")]
+        queue_group: Option<Bytes>,
+    },
+    #[serde(rename = "UNSUB")]
+    Unsubscribe {
+        sid: usize,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_messages: Option<usize>,
+    },
+    Invalid {
+        #[serde(with = "bytes")]
+        raw: Bytes,
+        reason: ProtocolError,
+    },
+}
+
+impl From<&Message> for Repr {
+    fn from(message: &Message) -> Self {
+        match message.clone() {
+            Message::Ok => Self::Ok,
+            Message::Err(p) => {
+                let (prefix, msg) = p.parts();
+
+                Self::Err {
+                    message: String::from_utf8_lossy(&[prefix, msg].concat()).into_owned(),
+                }
+            }
+            Message::Ping => Self::Ping,
+            Message::Pong => Self::Pong,
+            Message::Info(p) => Self::Info(p),
+            Message::Connect(p) => Self::Connect(p),
+            Message::RawInfo(p) => Self::RawInfo { json: p.into_raw() },
+            Message::RawConnect(p) => Self::RawConnect { json: p.into_raw() },
+            Message::Message(p) => Self::Message {
+                subject: p.subject,
+                sid: p.sid,
+                reply_to: p.reply_to,
+                body: p.payload,
+            },
+            Message::Publish(p) => Self::Publish {
+                subject: p.subject,
+                reply_to: p.reply_to,
+                body: p.payload,
+            },
+            Message::HMessage(p) => Self::HMessage {
+                subject: p.subject,
+                sid: p.sid,
+                reply_to: p.reply_to,
+                headers: p.headers,
+                body: p.payload,
+            },
+            Message::HPublish(p) => Self::HPublish {
+                subject: p.subject,
+                reply_to: p.reply_to,
+                headers: p.headers,
+                body: p.payload,
+            },
+            Message::Subscribe(p) => Self::Subscribe {
+                subject: p.subject,
+                sid: p.sid,
+                queue_group: p.queue_group,
+            },
+            Message::Unsubscribe(p) => Self::Unsubscribe {
+                sid: p.sid,
+                max_messages: p.max_messages,
+            },
+            Message::Invalid { raw, reason } => Self::Invalid { raw, reason },
+        }
+    }
+}
+
+impl From<Repr> for Message {
+    fn from(repr: Repr) -> Self {
+        let mut message = match repr {
+            Repr::Ok => Self::Ok,
+            Repr::Err { message } => Self::Err(error::Payload::from(message.as_str())),
+            Repr::Ping => Self::Ping,
+            Repr::Pong => Self::Pong,
+            Repr::Info(p) => Self::Info(p),
+            Repr::Connect(p) => Self::Connect(p),
+            Repr::RawInfo { json } => Self::RawInfo(Json::new(json)),
+            Repr::RawConnect { json } => Self::RawConnect(Json::new(json)),
+            Repr::Message {
+                subject,
+                sid,
+                reply_to,
+                body,
+            } => Self::Message(message::Payload {
+                subject,
+                sid,
+                reply_to,
+                payload_size: 0,
+                payload: body,
+            }),
+            Repr::Publish {
+                subject,
+                reply_to,
+                body,
+            } => Self::Publish(publish::Payload {
+                subject,
+                reply_to,
+                payload_size: 0,
+                payload: body,
+            }),
+            Repr::HMessage {
+                subject,
+                sid,
+                reply_to,
+                headers,
+                body,
+            } => Self::HMessage(hmessage::Payload {
+                subject,
+                sid,
+                reply_to,
+                headers,
+                payload: body,
+                ..Default::default()
+            }),
+            Repr::HPublish {
+                subject,
+                reply_to,
+                headers,
+                body,
+            } => Self::HPublish(hpublish::Payload {
+                subject,
+                reply_to,
+                headers,
+                payload: body,
+                ..Default::default()
+            }),
+            Repr::Subscribe {
+                subject,
+                sid,
+                queue_group,
+            } => Self::Subscribe(subscribe::Payload {
+                subject,
+                sid,
+                queue_group,
+            }),
+            Repr::Unsubscribe { sid, max_messages } => {
+                Self::Unsubscribe(unsubscribe::Payload { sid, max_messages })
+            }
+            Repr::Invalid { raw, reason } => Self::Invalid { raw, reason },
+        };
+
+        message.derive_sizes();
+        message
+    }
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Repr::from(self).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Repr::deserialize(deserializer).map(Message::from)
+    }
+}
+
+/// `serde(with)` for `Bytes`, UTF-8 text as is, anything else base64 encoded.
+pub(crate) mod bytes {
+    use ::bytes::Bytes;
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{ser::SerializeMap, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Data {
+        Utf8(String),
+        Base64 { base64: String },
+    }
+
+    pub fn serialize<S: Serializer>(data: &Bytes, serializer: S) -> Result<S::Ok, S::Error> {
+        match std::str::from_utf8(data) {
+            Ok(text) => serializer.serialize_str(text),
+            Err(_) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("base64", &STANDARD.encode(data))?;
+                map.end()
+            }
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Bytes, D::Error> {
+        match Data::deserialize(deserializer)? {
+            Data::Utf8(text) => Ok(Bytes::from(text)),
+            Data::Base64 { base64 } => STANDARD
+                .decode(base64)
+                .map(Bytes::from)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+// `serde(with)` for `Option<Bytes>`
+mod option {
+    use ::bytes::Bytes;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    struct Data<'a>(&'a Bytes);
+
+    impl Serialize for Data<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            super::bytes::serialize(self.0, serializer)
+        }
+    }
+
+    #[derive(Deserialize)]
+    struct Owned(#[serde(with = "super::bytes")] Bytes);
+
+    pub fn serialize<S: Serializer>(
+        data: &Option<Bytes>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        data.as_ref().map(Data).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Bytes>, D::Error> {
+        Ok(Option::<Owned>::deserialize(deserializer)?.map(|Owned(data)| data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::super::{error, Headers, Message};
+    use crate::error::ProtocolError;
+
+    #[test]
+    fn test_golden() {
+        let mut headers = Headers::new();
+        headers.insert("Foo", "bar");
+
+        let cases = [
+            (Message::Ping, r#"{"op":"PING"}"#),
+            (
+                Message::Err(error::Payload::StaleConnection),
+                r#"{"op":"ERR","message":"Stale Connection"}"#,
+            ),
+            (
+                Message::publish_with_reply("FOO", "INBOX.1", "hello"),
+                r#"{"op":"PUB","subject":"FOO","reply_to":"INBOX.1","body":"hello"}"#,
+            ),
+            (
+                Message::publish("FOO", Bytes::from_static(&[0xff, 0x00])),
+                r#"{"op":"PUB","subject":"FOO","body":{"base64":"/wA="}}"#,
+            ),
+            (
+                Message::hpublish("FOO", &headers, "").unwrap(),
+                r#"{"op":"HPUB","subject":"FOO","headers":"NATS/1.0\r\nFoo: bar\r\n\r\n","body":""}"#,
+            ),
+            (
+                Message::queue_subscribe("FOO", "workers", 1),
+                r#"{"op":"SUB","subject":"FOO","sid":1,"queue_group":"workers"}"#,
+            ),
+            (
+                Message::unsubscribe_after(1, 5),
+                r#"{"op":"UNSUB","sid":1,"max_messages":5}"#,
+            ),
+            (
+                Message::Invalid {
+                    raw: Bytes::from("PUB FOO x\r\n"),
+                    reason: ProtocolError::InvalidControlLine,
+                },
+                r#"{"op":"INVALID","raw":"PUB FOO x\r\n","reason":"invalid_control_line"}"#,
+            ),
+        ];
+
+        for (message, json) in cases {
+            assert_eq!(json, serde_json::to_string(&message).unwrap());
+            assert_eq!(message, serde_json::from_str::<Message>(json).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        use crate::Codec;
+        use bytes::BytesMut;
+        use tokio_util::codec::Decoder;
+
+        let mut buf = BytesMut::from(
+            &b"INFO {\"server_id\":\"id\",\"version\":\"2.9.0\",\"go\":\"go1.19\",\"host\":\"0.0.0.0\",\"port\":4222}\r\n\
+               MSG FOO 1 INBOX.1 5\r\nhello\r\n\
+               HMSG FOO 2 22 27\r\nNATS/1.0\r\nFoo: bar\r\n\r\nhello\r\n\
+               +OK\r\n"[..],
+        );
+
+        let mut codec = Codec::new();
+        let mut count = 0;
+
+        while let Some(message) = codec.decode(&mut buf).unwrap() {
+            let json = serde_json::to_string(&message).unwrap();
+
+            assert_eq!(message, serde_json::from_str::<Message>(&json).unwrap());
+            count += 1;
+        }
+
+        assert_eq!(4, count);
+    }
+}
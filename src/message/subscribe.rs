@@ -2,8 +2,10 @@ use std::io;
 
 use bytes::{BufMut, Bytes};
 
-const HEADER: &'static [u8] = &[b'S', b'U', b'B', b' '];
-const CLRF: &'static [u8] = &[b'\r', b'\n'];
+use super::{put_usize, usize_len};
+
+const HEADER: &[u8] = b"SUB ";
+const CLRF: &[u8] = b"\r\n";
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Payload {
@@ -25,10 +27,16 @@ impl Payload {
         };
 
         dst.put_slice(&b" "[..]);
-        dst.put_slice(&self.sid.to_string().as_bytes());
+        put_usize(dst, self.sid);
 
         dst.put_slice(CLRF);
 
         Ok(())
     }
+
+    pub fn encoded_len(&self) -> usize {
+        let queue_group = self.queue_group.as_ref().map_or(0, |q| 1 + q.len());
+
+        HEADER.len() + self.subject.len() + queue_group + 1 + usize_len(self.sid) + CLRF.len()
+    }
 }
@@ -0,0 +1,241 @@
+use std::fmt;
+
+use super::{connect, Message};
+
+const REDACTED: &str = "[REDACTED]";
+
+// CONNECT fields never written to logs
+const SECRETS: [&str; 4] = ["pass", "auth_token", "sig", "jwt"];
+
+/// Protocol line of a message, optionally followed by a body preview.
+///
+/// Bytes are escaped, CONNECT credentials are redacted, so the output can
+/// go straight into logs: `tracing::debug!(message = %message.trace().body(64))`.
+#[derive(Debug, Clone, Copy)]
+pub struct Trace<'a> {
+    message: &'a Message,
+    body: Option<usize>,
+}
+
+impl Message {
+    pub fn trace(&self) -> Trace<'_> {
+        Trace {
+            message: self,
+            body: None,
+        }
+    }
+}
+
+impl Trace<'_> {
+    /// Appends up to `max_len` bytes of the body.
+    pub fn body(mut self, max_len: usize) -> Self {
+        self.body = Some(max_len);
+
+        self
+    }
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.trace().fmt(f)
+    }
+}
+
+impl fmt::Display for Trace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message {
+            Message::Ok => f.write_str("+OK")?,
+            Message::Err(p) => {
+                let (prefix, msg) = p.parts();
+                write!(f, "-ERR '{}{}'", prefix.escape_ascii(), msg.escape_ascii())?
+            }
+            Message::Ping => f.write_str("PING")?,
+            Message::Pong => f.write_str("PONG")?,
+            Message::Info(p) => match serde_json::to_string(p) {
+                Ok(json) => write!(f, "INFO {}", json)?,
+                Err(_) => f.write_str("INFO")?,
+            },
+            Message::RawInfo(p) => write!(f, "INFO {}", p.raw().escape_ascii())?,
+            Message::Connect(p) => match serde_json::to_string(&p.redacted()) {
+                Ok(json) => write!(f, "CONNECT {}", json)?,
+                Err(_) => f.write_str("CONNECT")?,
+            },
+            // only what parses can be redacted
+            Message::RawConnect(p) => match serde_json::from_slice(p.raw()) {
+                Ok(mut json) => {
+                    redact(&mut json);
+                    write!(f, "CONNECT {}", json)?
+                }
+                Err(_) => write!(f, "CONNECT <{} bytes>", p.raw().len())?,
+            },
+            Message::Message(p) => {
+                write!(f, "MSG {} {}", p.subject.escape_ascii(), p.sid)?;
+                if let Some(reply_to) = &p.reply_to {
+                    write!(f, " {}", reply_to.escape_ascii())?;
+                }
+                write!(f, " {}", p.payload_size)?
+            }
+            Message::Publish(p) => {
+                write!(f, "PUB {}", p.subject.escape_ascii())?;
+                if let Some(reply_to) = &p.reply_to {
+                    write!(f, " {}", reply_to.escape_ascii())?;
+                }
+                write!(f, " {}", p.payload_size)?
+            }
+            Message::HMessage(p) => {
+                write!(f, "HMSG {} {}", p.subject.escape_ascii(), p.sid)?;
+                if let Some(reply_to) = &p.reply_to {
+                    write!(f, " {}", reply_to.escape_ascii())?;
+                }
+                write!(f, " {} {}", p.header_size, p.total_size)?
+            }
+            Message::HPublish(p) => {
+                write!(f, "HPUB {}", p.subject.escape_ascii())?;
+                if let Some(reply_to) = &p.reply_to {
+                    write!(f, " {}", reply_to.escape_ascii())?;
+                }
+                write!(f, " {} {}", p.header_size, p.total_size)?
+            }
+            Message::Subscribe(p) => {
+                write!(f, "SUB {}", p.subject.escape_ascii())?;
+                if let Some(queue_group) = &p.queue_group {
+                    write!(f, " {}", queue_group.escape_ascii())?;
+                }
+                write!(f, " {}", p.sid)?
+            }
+            Message::Unsubscribe(p) => {
+                write!(f, "UNSUB {}", p.sid)?;
+                if let Some(max_messages) = p.max_messages {
+                    write!(f, " {}", max_messages)?;
+                }
+            }
+            // the raw frame may be anything, a CONNECT with credentials included
+            Message::Invalid { raw, reason } => {
+                write!(f, "INVALID <{} bytes>: {}", raw.len(), reason)?
+            }
+        }
+
+        match (self.body, self.message.body()) {
+            (Some(max_len), Some(body)) if body.len() > max_len => write!(
+                f,
+                " \"{}\"... (+{} bytes)",
+                body[..max_len].escape_ascii(),
+                body.len() - max_len
+            ),
+            (Some(_), Some(body)) => write!(f, " \"{}\"", body.escape_ascii()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl connect::Payload {
+    /// Copy with `pass`, `auth_token`, `sig` and `jwt` masked.
+    pub fn redacted(&self) -> Self {
+        let mask = |s: &Option<String>| s.as_ref().map(|_| REDACTED.to_string());
+
+        Self {
+            auth_token: mask(&self.auth_token),
+            pass: mask(&self.pass),
+            sig: mask(&self.sig),
+            jwt: mask(&self.jwt),
+            ..self.clone()
+        }
+    }
+}
+
+fn redact(json: &mut serde_json::Value) {
+    if let Some(object) = json.as_object_mut() {
+        for key in SECRETS {
+            if let Some(value) = object.get_mut(key) {
+                *value = REDACTED.into();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::super::{connect, json::Json, Headers, Message};
+    use crate::error::ProtocolError;
+
+    #[test]
+    fn test_display() {
+        let mut headers = Headers::new();
+        headers.insert("Foo", "bar");
+
+        let cases = [
+            (Message::Ok, "+OK"),
+            (Message::Pong, "PONG"),
+            (
+                Message::publish_with_reply("foo.bar", "INBOX.1", "hello world"),
+                "PUB foo.bar INBOX.1 11",
+            ),
+            (
+                Message::hpublish("foo", &headers, "hi").unwrap(),
+                "HPUB foo 22 24",
+            ),
+            (Message::queue_subscribe("foo", "q", 1), "SUB foo q 1"),
+            (Message::unsubscribe_after(1, 5), "UNSUB 1 5"),
+            (Message::publish("foo\r\n", ""), "PUB foo\\r\\n 0"),
+            (
+                Message::Invalid {
+                    raw: Bytes::from("PUB x\r\n"),
+                    reason: ProtocolError::InvalidControlLine,
+                },
+                "INVALID <7 bytes>: invalid control line",
+            ),
+        ];
+
+        for (message, line) in cases {
+            assert_eq!(line, message.to_string());
+        }
+    }
+
+    #[test]
+    fn test_body_preview() {
+        let message = Message::publish("foo", "hello\nworld");
+
+        assert_eq!(
+            "PUB foo 11 \"hello\\nworld\"",
+            message.trace().body(64).to_string()
+        );
+        assert_eq!(
+            "PUB foo 11 \"hello\"... (+6 bytes)",
+            message.trace().body(5).to_string()
+        );
+        assert_eq!("PING", Message::Ping.trace().body(5).to_string());
+    }
+
+    #[test]
+    fn test_redaction() {
+        let message = Message::Connect(connect::Payload {
+            user: Some("derek".into()),
+            pass: Some("s3cr3t".into()),
+            jwt: Some("eyJ0".into()),
+            ..Default::default()
+        });
+
+        let line = message.to_string();
+
+        assert!(line.starts_with("CONNECT {"));
+        assert!(line.contains("\"user\":\"derek\""));
+        assert!(line.contains("\"pass\":\"[REDACTED]\""));
+        assert!(line.contains("\"jwt\":\"[REDACTED]\""));
+        assert!(!line.contains("s3cr3t") && !line.contains("eyJ0"));
+
+        let message = Message::RawConnect(Json::new(Bytes::from(
+            "{\"verbose\":false,\"auth_token\":\"t0k3n\"}",
+        )));
+
+        assert_eq!(
+            "CONNECT {\"auth_token\":\"[REDACTED]\",\"verbose\":false}",
+            message.to_string()
+        );
+
+        let message = Message::RawConnect(Json::new(Bytes::from("{\"pass\":\"s3cr")));
+
+        assert_eq!("CONNECT <13 bytes>", message.to_string());
+    }
+}
@@ -2,8 +2,10 @@ use std::io;
 
 use bytes::BufMut;
 
-const HEADER: &'static [u8] = &[b'U', b'N', b'S', b'U', b'B', b' '];
-const CLRF: &'static [u8] = &[b'\r', b'\n'];
+use super::{put_usize, usize_len};
+
+const HEADER: &[u8] = b"UNSUB ";
+const CLRF: &[u8] = b"\r\n";
 
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct Payload {
@@ -17,16 +19,21 @@ impl Payload {
     pub fn encode(&self, dst: &mut bytes::BytesMut) -> Result<(), io::Error> {
         dst.put_slice(HEADER);
 
-        dst.put_slice(&b" "[..]);
-        dst.put_slice(&self.sid.to_string().as_bytes());
+        put_usize(dst, self.sid);
 
         if let Some(max_messages) = &self.max_messages {
             dst.put_slice(&b" "[..]);
-            dst.put_slice(max_messages.to_string().as_bytes());
+            put_usize(dst, *max_messages);
         };
 
         dst.put_slice(CLRF);
 
         Ok(())
     }
+
+    pub fn encoded_len(&self) -> usize {
+        let max_messages = self.max_messages.map_or(0, |n| 1 + usize_len(n));
+
+        HEADER.len() + usize_len(self.sid) + max_messages + CLRF.len()
+    }
 }
@@ -0,0 +1,259 @@
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::error::ProtocolError;
+use super::message::op::Op;
+
+/// Hooks called by `Codec`, every method defaults to a no-op.
+///
+/// Called inline on the decode/encode path, implementations should stick to
+/// atomics.
+pub trait CodecMetrics: fmt::Debug + Send + Sync {
+    /// A complete frame of `len` bytes, CRLFs included, was decoded.
+    fn frame_decoded(&self, _op: Op, _len: usize) {}
+
+    /// A frame of `len` bytes was encoded.
+    fn frame_encoded(&self, _op: Op, _len: usize) {}
+
+    /// `decode` returned for lack of input with a partial frame buffered.
+    fn partial_frame(&self) {}
+
+    /// Size of the read buffer on each `decode` call.
+    fn buffered(&self, _len: usize) {}
+
+    /// A frame exceeded `Codec::with_large_frame_threshold`.
+    fn large_frame(&self, _op: Op, _len: usize) {}
+
+    /// Frame rejected, or skipped in recovery mode.
+    fn decode_error(&self, _reason: &ProtocolError) {}
+
+    /// Message rejected by `Message::validate`.
+    fn encode_error(&self, _reason: &ProtocolError) {}
+}
+
+/// Default, records nothing.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMetrics;
+
+impl CodecMetrics for NoopMetrics {}
+
+const OPS: [Op; Op::COUNT] = [
+    Op::Ok,
+    Op::Err,
+    Op::Info,
+    Op::Ping,
+    Op::Pong,
+    Op::Publish,
+    Op::Connect,
+    Op::Message,
+    Op::HPublish,
+    Op::HMessage,
+    Op::Subscribe,
+    Op::Unsubscribe,
+];
+
+// every op is listed once, at its own index
+const _: () = {
+    let mut i = 0;
+
+    while i < OPS.len() {
+        assert!(OPS[i].index() == i);
+        i += 1;
+    }
+};
+
+#[derive(Debug, Default)]
+struct PerOp([AtomicU64; Op::COUNT]);
+
+impl PerOp {
+    #[inline]
+    fn add(&self, op: Op, n: usize) {
+        self.0[op.index()].fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    fn get(&self, op: Op) -> u64 {
+        self.0[op.index()].load(Ordering::Relaxed)
+    }
+}
+
+/// Counters kept in atomics, rendered in the Prometheus text format by
+/// [`Counters::render`].
+#[derive(Debug, Default)]
+pub struct Counters {
+    frames_in: PerOp,
+    bytes_in: PerOp,
+    frames_out: PerOp,
+    bytes_out: PerOp,
+    large_frames: PerOp,
+    partial_frames: AtomicU64,
+    decode_errors: AtomicU64,
+    encode_errors: AtomicU64,
+    buffer_high_water: AtomicU64,
+}
+
+impl CodecMetrics for Counters {
+    fn frame_decoded(&self, op: Op, len: usize) {
+        self.frames_in.add(op, 1);
+        self.bytes_in.add(op, len);
+    }
+
+    fn frame_encoded(&self, op: Op, len: usize) {
+        self.frames_out.add(op, 1);
+        self.bytes_out.add(op, len);
+    }
+
+    fn partial_frame(&self) {
+        self.partial_frames.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn buffered(&self, len: usize) {
+        self.buffer_high_water
+            .fetch_max(len as u64, Ordering::Relaxed);
+    }
+
+    fn large_frame(&self, op: Op, _len: usize) {
+        self.large_frames.add(op, 1);
+    }
+
+    fn decode_error(&self, _reason: &ProtocolError) {
+        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn encode_error(&self, _reason: &ProtocolError) {
+        self.encode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Counters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn frames_in(&self, op: Op) -> u64 {
+        self.frames_in.get(op)
+    }
+
+    pub fn bytes_in(&self, op: Op) -> u64 {
+        self.bytes_in.get(op)
+    }
+
+    pub fn frames_out(&self, op: Op) -> u64 {
+        self.frames_out.get(op)
+    }
+
+    pub fn bytes_out(&self, op: Op) -> u64 {
+        self.bytes_out.get(op)
+    }
+
+    pub fn partial_frames(&self) -> u64 {
+        self.partial_frames.load(Ordering::Relaxed)
+    }
+
+    pub fn decode_errors(&self) -> u64 {
+        self.decode_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn encode_errors(&self) -> u64 {
+        self.encode_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn buffer_high_water(&self) -> u64 {
+        self.buffer_high_water.load(Ordering::Relaxed)
+    }
+
+    /// Prometheus text exposition, metric names prefixed with `prefix`.
+    pub fn render(&self, prefix: &str) -> String {
+        let mut out = String::new();
+
+        let per_op = [
+            ("frames_in_total", "Frames decoded", &self.frames_in),
+            ("bytes_in_total", "Bytes decoded", &self.bytes_in),
+            ("frames_out_total", "Frames encoded", &self.frames_out),
+            ("bytes_out_total", "Bytes encoded", &self.bytes_out),
+            (
+                "large_frames_total",
+                "Frames over the size threshold",
+                &self.large_frames,
+            ),
+        ];
+
+        // writing into a String can't fail
+        for (name, help, counter) in per_op {
+            let _ = writeln!(out, "# HELP {prefix}_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_{name} counter");
+
+            for op in OPS {
+                let _ = writeln!(
+                    out,
+                    "{prefix}_{name}{{op=\"{}\"}} {}",
+                    op.as_str(),
+                    counter.get(op)
+                );
+            }
+        }
+
+        let single = [
+            (
+                "partial_frames_total",
+                "counter",
+                "Decode calls waiting for more input",
+                self.partial_frames(),
+            ),
+            (
+                "decode_errors_total",
+                "counter",
+                "Frames rejected by the decoder",
+                self.decode_errors(),
+            ),
+            (
+                "encode_errors_total",
+                "counter",
+                "Messages rejected by the encoder",
+                self.encode_errors(),
+            ),
+            (
+                "buffer_high_water_bytes",
+                "gauge",
+                "Largest read buffer seen",
+                self.buffer_high_water(),
+            ),
+        ];
+
+        for (name, kind, help, value) in single {
+            let _ = writeln!(out, "# HELP {prefix}_{name} {help}");
+            let _ = writeln!(out, "# TYPE {prefix}_{name} {kind}");
+            let _ = writeln!(out, "{prefix}_{name} {value}");
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodecMetrics, Counters, Op};
+
+    #[test]
+    fn test_counters() {
+        let counters = Counters::new();
+
+        counters.frame_decoded(Op::Message, 20);
+        counters.frame_decoded(Op::Message, 30);
+        counters.frame_encoded(Op::Publish, 10);
+        counters.buffered(100);
+        counters.buffered(50);
+
+        assert_eq!(2, counters.frames_in(Op::Message));
+        assert_eq!(50, counters.bytes_in(Op::Message));
+        assert_eq!(1, counters.frames_out(Op::Publish));
+        assert_eq!(0, counters.frames_out(Op::Message));
+        assert_eq!(100, counters.buffer_high_water());
+
+        let text = counters.render("nats_codec");
+
+        assert!(text.contains("# TYPE nats_codec_frames_in_total counter\n"));
+        assert!(text.contains("nats_codec_frames_in_total{op=\"MSG\"} 2\n"));
+        assert!(text.contains("nats_codec_bytes_out_total{op=\"PUB\"} 10\n"));
+        assert!(text.contains("nats_codec_buffer_high_water_bytes 100\n"));
+    }
+}
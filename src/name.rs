@@ -0,0 +1,7 @@
+// service, endpoint, bucket and store names, `[A-Za-z0-9_-]+`
+pub(crate) fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|c| c.is_ascii_alphanumeric() || c == b'_' || c == b'-')
+}
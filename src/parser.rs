@@ -1,94 +1,253 @@
-use atoi::FromRadix10;
+use atoi::FromRadix10Checked;
 use bytes::{Buf, Bytes, BytesMut};
 
-use nom::combinator::opt;
-use nom::sequence::{delimited, preceded, terminated, tuple};
+use nom::branch::alt;
+use nom::combinator::{map, opt};
+use nom::error::{ErrorKind, ParseError};
+use nom::sequence::{preceded, terminated, tuple};
 use nom::Needed;
 
+use super::error::ProtocolError;
 use super::message;
-use super::message::{op::Op, Message};
+use super::message::{json::Json, op::Op, Message};
+use super::tokenizer;
 
-pub type ParseResult<O> = Result<O, nom::Err<nom::error::Error<Bytes>>>;
+pub type ParseResult<O> = Result<O, nom::Err<Error>>;
 
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    // input doesn't match the grammar
+    Syntax(nom::error::Error<Bytes>),
+    // well formed, but violates the protocol
+    Protocol(ProtocolError),
+}
+
+impl Error {
+    #[inline]
+    pub fn new(input: Bytes, code: ErrorKind) -> Self {
+        Error::Syntax(nom::error::Error::new(input, code))
+    }
+}
+
+impl ParseError<Bytes> for Error {
+    fn from_error_kind(input: Bytes, kind: ErrorKind) -> Self {
+        Error::new(input, kind)
+    }
+
+    fn append(_: Bytes, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl From<Error> for ProtocolError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Syntax(_) => ProtocolError::InvalidControlLine,
+            Error::Protocol(e) => e,
+        }
+    }
+}
+
+// protocol violations are failures, so `opt`/`alt` don't swallow them
+#[inline]
+fn failure(e: ProtocolError) -> nom::Err<Error> {
+    nom::Err::Failure(Error::Protocol(e))
+}
+
+/// How closely the control lines have to follow the protocol grammar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Exact grammar: uppercase ops, arguments separated by a single space
+    /// and nothing after the last argument.
+    Strict,
+    /// Tolerates what real servers and old clients emit: any op case, runs
+    /// of spaces and tabs, trailing whitespace and garbage, unquoted `-ERR`
+    /// text.
+    #[default]
+    Lenient,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    // keep INFO/CONNECT json undecoded
+    pub lazy_json: bool,
+    pub mode: ParseMode,
+}
+
+#[cfg(test)]
 #[inline]
 pub fn parse(input: Bytes) -> ParseResult<Message> {
-    let (input, op) = op(input)?;
+    parse_with(input, &Options::default())
+}
+
+#[inline]
+pub fn parse_with(input: Bytes, options: &Options) -> ParseResult<Message> {
+    let strict = options.mode == ParseMode::Strict;
+
+    let input = if strict { input } else { trim_end(input) };
+
+    let (input, op) = op(input, strict)?;
 
-    let message = match op {
-        // hottest
-        Op::Publish => parse_publish(input)?,
-        Op::Message => parse_message(input)?,
+    if strict {
+        single_spaced(&input, op)?;
+    }
+
+    let (input, message) = match op {
+        // hottest, `simd` picks the memchr tokenizer over nom
+        #[cfg(feature = "simd")]
+        Op::Publish => parse_publish_memchr(input)?,
+        #[cfg(not(feature = "simd"))]
+        Op::Publish => parse_publish_nom(input)?,
+        #[cfg(feature = "simd")]
+        Op::Message => parse_message_memchr(input)?,
+        #[cfg(not(feature = "simd"))]
+        Op::Message => parse_message_nom(input)?,
+        Op::HPublish => parse_hpublish(input)?,
+        Op::HMessage => parse_hmessage(input)?,
         //
-        Op::Ok => Message::Ok,
-        Op::Err => parse_error(input)?,
-        Op::Info => parse_info(input)?,
-        Op::Ping => Message::Ping,
-        Op::Pong => Message::Pong,
-        Op::Connect => parse_connect(input)?,
+        Op::Ok => (input, Message::Ok),
+        Op::Err => parse_error(input, strict)?,
+        Op::Info => parse_info(input, options)?,
+        Op::Ping => (input, Message::Ping),
+        Op::Pong => (input, Message::Pong),
+        Op::Connect => parse_connect(input, options)?,
         Op::Subscribe => parse_subscribe(input)?,
         Op::Unsubscribe => parse_unsubscribe(input)?,
     };
 
+    // lenient mode ignores whatever follows the arguments, PUB/MSG aside
+    if strict && !input.is_empty() {
+        let code = nom::error::ErrorKind::Eof;
+
+        return Err(nom::Err::Error(Error::new(input, code)));
+    }
+
     Ok(message)
 }
 
+/// Both PUB/MSG control line parsers regardless of `simd`, so the decode
+/// bench can compare them in a single run.
+#[doc(hidden)]
+pub mod bench {
+    use bytes::Bytes;
+
+    use super::{op, Error, Message, Op, ParseResult, ProtocolError};
+    use super::{parse_message_memchr, parse_message_nom, parse_publish_memchr, parse_publish_nom};
+
+    type Parser = fn(Bytes) -> ParseResult<(Bytes, Message)>;
+
+    pub fn nom(line: Bytes) -> Result<Message, ProtocolError> {
+        parse(line, parse_publish_nom, parse_message_nom)
+    }
+
+    pub fn memchr(line: Bytes) -> Result<Message, ProtocolError> {
+        parse(line, parse_publish_memchr, parse_message_memchr)
+    }
+
+    fn parse(line: Bytes, publish: Parser, message: Parser) -> Result<Message, ProtocolError> {
+        let (input, op) = op(line, false).map_err(protocol)?;
+
+        let parser = match op {
+            Op::Publish => publish,
+            Op::Message => message,
+            _ => return Err(ProtocolError::InvalidControlLine),
+        };
+
+        parser(input).map(|(_, message)| message).map_err(protocol)
+    }
+
+    fn protocol(e: nom::Err<Error>) -> ProtocolError {
+        match e {
+            nom::Err::Error(e) | nom::Err::Failure(e) => e.into(),
+            nom::Err::Incomplete(_) => ProtocolError::InvalidControlLine,
+        }
+    }
+}
+
 #[inline]
-fn op(input: Bytes) -> ParseResult<(Bytes, Op)> {
+fn op(input: Bytes, strict: bool) -> ParseResult<(Bytes, Op)> {
     let (input, value) = ident1(input)?;
 
     let op = Op::try_from(&value[..]).map_err(|_| {
         let code = nom::error::ErrorKind::Tag;
 
-        nom::Err::Error(nom::error::Error::new(input.clone(), code))
+        nom::Err::Error(Error::new(input.clone(), code))
     })?;
 
+    if strict && value.iter().any(u8::is_ascii_lowercase) {
+        let code = nom::error::ErrorKind::Tag;
+
+        return Err(nom::Err::Error(Error::new(value, code)));
+    }
+
     Ok((input, op))
 }
 
+// strict grammar: a single space before every argument and no tabs
 #[inline]
-fn parse_error(input: Bytes) -> ParseResult<Message> {
+fn single_spaced(input: &Bytes, op: Op) -> ParseResult<()> {
+    let args = match op {
+        // json and error text are free form after the separator
+        Op::Info | Op::Connect | Op::Err => input.get(..2).unwrap_or(input),
+        _ => input,
+    };
+
+    let valid = !args.contains(&b'\t')
+        && !args.windows(2).any(|w| w == b"  ")
+        && args.last() != Some(&b' ');
+
+    if !valid {
+        let code = nom::error::ErrorKind::Space;
+
+        return Err(nom::Err::Error(Error::new(input.clone(), code)));
+    }
+
+    Ok(())
+}
+
+#[inline]
+fn parse_error(input: Bytes, strict: bool) -> ParseResult<(Bytes, Message)> {
     use message::error::Payload;
 
-    let cond =
-        |c| matches!(c, b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.'| b'*'| b'_' | b'-' | b' ');
+    let (input, _) = space1(input)?;
 
-    let (_, raw) = preceded(
-        space1,
-        delimited(
-            // '
-            tag_u8(b'\''),
-            // error message
-            take_while1(cond),
-            // '
-            tag_u8(b'\''),
-        ),
-    )(input)?;
+    // servers quote the text, but be tolerant to the unquoted form
+    let (input, raw) = match quoted(input.clone()) {
+        Ok((input, raw)) => (input, raw),
+        Err(e) if strict => return Err(e),
+        Err(_) => (Bytes::new(), input),
+    };
 
-    let err: Payload = (&raw[..]).into();
+    // the text is kept byte for byte, utf8 or not
+    let err = Payload::from(raw);
 
-    Ok(Message::Err(err))
+    Ok((input, Message::Err(err)))
 }
 
 #[inline]
-fn parse_info(input: Bytes) -> ParseResult<Message> {
+fn parse_info(input: Bytes, options: &Options) -> ParseResult<(Bytes, Message)> {
     use message::info::Payload;
 
     let (input, _) = space1(input)?;
 
+    if options.lazy_json {
+        return Ok((Bytes::new(), Message::RawInfo(Json::new(input))));
+    }
+
     let payload = serde_json::from_slice::<Payload>(&input).map_err(|_| {
         let code = nom::error::ErrorKind::Fail;
 
-        nom::Err::Error(nom::error::Error::new(input.clone(), code))
+        nom::Err::Error(Error::new(input.clone(), code))
     })?;
 
-    Ok(Message::Info(payload))
+    Ok((Bytes::new(), Message::Info(payload)))
 }
 
 #[inline]
-fn parse_publish(input: Bytes) -> ParseResult<Message> {
+fn parse_publish_nom(input: Bytes) -> ParseResult<(Bytes, Message)> {
     use message::publish::Payload;
 
-    let (_, (subject, reply_to, payload_size)) = preceded(
+    let (input, (subject, reply_to, payload_size)) = preceded(
         space1,
         tuple((
             // subject
@@ -96,65 +255,254 @@ fn parse_publish(input: Bytes) -> ParseResult<Message> {
             // reply_to
             opt(terminated(ident1, space1)),
             // payload_size
-            digit1,
+            number1,
         )),
     )(input)?;
+    let input = no_more_fields(input)?;
 
-    Ok(Message::Publish(Payload {
+    let message = Message::Publish(Payload {
         subject,
         reply_to,
         payload_size,
         payload: None,
-    }))
+    });
+
+    Ok((input, message))
+}
+
+#[inline]
+fn parse_publish_memchr(input: Bytes) -> ParseResult<(Bytes, Message)> {
+    use message::publish::Payload;
+
+    let mut fields = [&[][..]; 3];
+
+    let (subject, reply_to, payload_size) = match tokenizer::split(&input, &mut fields) {
+        Some(2) => (fields[0], None, fields[1]),
+        Some(3) => (fields[0], Some(fields[1]), fields[2]),
+        _ => {
+            return Err(nom::Err::Error(Error::new(
+                input,
+                nom::error::ErrorKind::Count,
+            )))
+        }
+    };
+
+    let payload_size = tokenizer::parse_usize(payload_size).map_err(failure)?;
+
+    let message = Message::Publish(Payload {
+        subject: input.slice_ref(subject),
+        reply_to: reply_to.map(|reply_to| input.slice_ref(reply_to)),
+        payload_size,
+        payload: None,
+    });
+
+    // the tokenizer consumes every field
+    Ok((Bytes::new(), message))
 }
 
 #[inline]
-fn parse_connect(input: Bytes) -> ParseResult<Message> {
+fn parse_connect(input: Bytes, options: &Options) -> ParseResult<(Bytes, Message)> {
     use message::connect::Payload;
 
     let (input, _) = space1(input)?;
 
+    if options.lazy_json {
+        return Ok((Bytes::new(), Message::RawConnect(Json::new(input))));
+    }
+
     let payload = serde_json::from_slice::<Payload>(&input).map_err(|_| {
         let code = nom::error::ErrorKind::Fail;
 
-        nom::Err::Error(nom::error::Error::new(input.clone(), code))
+        nom::Err::Error(Error::new(input.clone(), code))
     })?;
 
-    Ok(Message::Connect(payload))
+    Ok((Bytes::new(), Message::Connect(payload)))
 }
 
 #[inline]
-fn parse_message(input: Bytes) -> ParseResult<Message> {
+fn parse_message_nom(input: Bytes) -> ParseResult<(Bytes, Message)> {
     use message::message::Payload;
 
-    let (_, (subject, sid, reply_to, payload_size)) = preceded(
+    let (input, (subject, sid, reply_to, payload_size)) = preceded(
         space1,
         tuple((
             // subject
             terminated(ident1, space1),
             // sid
-            terminated(digit1, space1),
+            terminated(number1, space1),
             // reply_to
             opt(terminated(ident1, space1)),
             // payload_size
-            digit1,
+            number1,
         )),
     )(input)?;
+    let input = no_more_fields(input)?;
 
-    Ok(Message::Message(Payload {
+    let message = Message::Message(Payload {
         subject,
         sid,
         reply_to,
         payload_size,
         payload: None,
-    }))
+    });
+
+    Ok((input, message))
+}
+
+#[inline]
+fn parse_message_memchr(input: Bytes) -> ParseResult<(Bytes, Message)> {
+    use message::message::Payload;
+
+    let mut fields = [&[][..]; 4];
+
+    let (subject, sid, reply_to, payload_size) = match tokenizer::split(&input, &mut fields) {
+        Some(3) => (fields[0], fields[1], None, fields[2]),
+        Some(4) => (fields[0], fields[1], Some(fields[2]), fields[3]),
+        _ => {
+            return Err(nom::Err::Error(Error::new(
+                input,
+                nom::error::ErrorKind::Count,
+            )))
+        }
+    };
+
+    let sid = tokenizer::parse_usize(sid).map_err(failure)?;
+    let payload_size = tokenizer::parse_usize(payload_size).map_err(failure)?;
+
+    let message = Message::Message(Payload {
+        subject: input.slice_ref(subject),
+        sid,
+        reply_to: reply_to.map(|reply_to| input.slice_ref(reply_to)),
+        payload_size,
+        payload: None,
+    });
+
+    // the tokenizer consumes every field
+    Ok((Bytes::new(), message))
+}
+
+// the size is the last PUB/MSG field, extra ones are rejected in lenient
+// mode too, as the tokenizer does
+#[inline]
+fn no_more_fields(input: Bytes) -> Result<Bytes, nom::Err<Error>> {
+    if !tokenizer::trim_start(&input).is_empty() {
+        let code = nom::error::ErrorKind::Count;
+
+        return Err(nom::Err::Error(Error::new(input, code)));
+    }
+
+    Ok(Bytes::new())
+}
+
+#[inline]
+fn parse_hpublish(input: Bytes) -> ParseResult<(Bytes, Message)> {
+    use message::hpublish::Payload;
+
+    let (input, (subject, reply_to, (header_size, total_size))) = preceded(
+        space1,
+        alt((
+            map(
+                tuple((
+                    // subject
+                    terminated(ident1, space1),
+                    // reply_to
+                    terminated(ident1, space1),
+                    // header_size, total_size
+                    sizes,
+                )),
+                |(subject, reply_to, sizes)| (subject, Some(reply_to), sizes),
+            ),
+            map(
+                tuple((
+                    // subject
+                    terminated(ident1, space1),
+                    // header_size, total_size
+                    sizes,
+                )),
+                |(subject, sizes)| (subject, None, sizes),
+            ),
+        )),
+    )(input)?;
+
+    let message = Message::HPublish(Payload {
+        subject,
+        reply_to,
+        header_size,
+        total_size,
+        headers: None,
+        payload: None,
+    });
+
+    Ok((input, message))
 }
 
 #[inline]
-fn parse_subscribe(input: Bytes) -> ParseResult<Message> {
+fn parse_hmessage(input: Bytes) -> ParseResult<(Bytes, Message)> {
+    use message::hmessage::Payload;
+
+    let (input, (subject, sid, reply_to, (header_size, total_size))) = preceded(
+        space1,
+        alt((
+            map(
+                tuple((
+                    // subject
+                    terminated(ident1, space1),
+                    // sid
+                    terminated(digit1, space1),
+                    // reply_to
+                    terminated(ident1, space1),
+                    // header_size, total_size
+                    sizes,
+                )),
+                |(subject, sid, reply_to, sizes)| (subject, sid, Some(reply_to), sizes),
+            ),
+            map(
+                tuple((
+                    // subject
+                    terminated(ident1, space1),
+                    // sid
+                    terminated(digit1, space1),
+                    // header_size, total_size
+                    sizes,
+                )),
+                |(subject, sid, sizes)| (subject, sid, None, sizes),
+            ),
+        )),
+    )(input)?;
+
+    let message = Message::HMessage(Payload {
+        subject,
+        sid,
+        reply_to,
+        header_size,
+        total_size,
+        headers: None,
+        payload: None,
+    });
+
+    Ok((input, message))
+}
+
+// <#header bytes> <#total bytes>
+#[inline]
+fn sizes(input: Bytes) -> ParseResult<(Bytes, (usize, usize))> {
+    let (input, (header_size, total_size)) = tuple((terminated(digit1, space1), digit1))(input)?;
+
+    if header_size > total_size {
+        return Err(failure(ProtocolError::HeaderSizeExceedsTotal {
+            header_size,
+            total_size,
+        }));
+    }
+
+    Ok((input, (header_size, total_size)))
+}
+
+#[inline]
+fn parse_subscribe(input: Bytes) -> ParseResult<(Bytes, Message)> {
     use message::subscribe::Payload;
 
-    let (_, (subject, queue_group, sid)) = preceded(
+    let (input, (subject, queue_group, sid)) = preceded(
         space1,
         tuple((
             // subject
@@ -166,18 +514,20 @@ fn parse_subscribe(input: Bytes) -> ParseResult<Message> {
         )),
     )(input)?;
 
-    Ok(Message::Subscribe(Payload {
+    let message = Message::Subscribe(Payload {
         subject,
         sid,
         queue_group,
-    }))
+    });
+
+    Ok((input, message))
 }
 
 #[inline]
-fn parse_unsubscribe(input: Bytes) -> ParseResult<Message> {
+fn parse_unsubscribe(input: Bytes) -> ParseResult<(Bytes, Message)> {
     use message::unsubscribe::Payload;
 
-    let (_, (sid, max_messages)) = preceded(
+    let (input, (sid, max_messages)) = preceded(
         space1,
         tuple((
             // sid
@@ -187,30 +537,22 @@ fn parse_unsubscribe(input: Bytes) -> ParseResult<Message> {
         )),
     )(input)?;
 
-    Ok(Message::Unsubscribe(Payload { sid, max_messages }))
+    let message = Message::Unsubscribe(Payload { sid, max_messages });
+
+    Ok((input, message))
 }
 
+/// Takes the control line off `input` together with its CRLF, `None` until
+/// it's complete.
 #[inline]
-pub fn cl1(input: &mut bytes::BytesMut) -> nom::IResult<&mut BytesMut, Bytes> {
-    use memchr::memchr;
-
-    let found = memchr(b'\r', input).and_then(|r_idx| {
-        let n_idx = input.get(r_idx + 1).and_then(|v| Some(*v == b'\n'));
-
-        Some((r_idx, n_idx))
-    });
-
-    let idx = match found {
-        Some((idx, Some(_))) => idx,
-        _ => {
-            return Err(nom::Err::Incomplete(nom::Needed::Unknown));
-        }
-    };
+pub fn cl1(input: &mut BytesMut) -> Option<Bytes> {
+    let idx = memchr::memchr(b'\r', input)?;
 
-    let cl = input.split_to(idx);
-    input.advance(2);
+    if input.len() < idx + 2 {
+        return None;
+    }
 
-    Ok((input, cl.freeze()))
+    Some(input.split_to(idx + 2).freeze())
 }
 
 #[inline]
@@ -220,17 +562,26 @@ fn space1(input: Bytes) -> ParseResult<(Bytes, ())> {
     skip_while1(cond)(input)
 }
 
+#[inline]
+fn trim_end(mut input: Bytes) -> Bytes {
+    let len = input.iter().rposition(|c| !matches!(c, b' ' | b'\t'));
+
+    input.truncate(len.map_or(0, |idx| idx + 1));
+
+    input
+}
+
 #[inline]
 fn tag_u8(b: u8) -> impl Fn(Bytes) -> ParseResult<(Bytes, ())> {
     move |mut input| {
-        if input.len() < 1 {
+        if input.is_empty() {
             return Err(nom::Err::Incomplete(Needed::new(1)));
         };
 
-        if !input[0] == b {
+        if input[0] != b {
             let code = nom::error::ErrorKind::Tag;
 
-            return Err(nom::Err::Error(nom::error::Error::new(input, code)));
+            return Err(nom::Err::Error(Error::new(input, code)));
         };
 
         input.advance(1);
@@ -239,6 +590,29 @@ fn tag_u8(b: u8) -> impl Fn(Bytes) -> ParseResult<(Bytes, ())> {
     }
 }
 
+/// Text between a leading quote and the last one, the rest of `input` after
+/// the closing quote.
+#[inline]
+pub fn quoted(input: Bytes) -> ParseResult<(Bytes, Bytes)> {
+    let (mut input, _) = tag_u8(b'\'')(input)?;
+
+    // the text itself may contain quotes, so the last one closes it
+    let idx = match memchr::memrchr(b'\'', &input) {
+        Some(idx) => idx,
+        None => {
+            let code = nom::error::ErrorKind::TakeUntil;
+
+            return Err(nom::Err::Error(Error::new(input, code)));
+        }
+    };
+
+    let found = input.split_to(idx);
+    input.advance(1);
+
+    Ok((input, found))
+}
+
+#[allow(dead_code)]
 #[inline]
 fn clrf1(mut input: Bytes) -> ParseResult<(Bytes, ())> {
     if input.len() < 2 {
@@ -250,7 +624,7 @@ fn clrf1(mut input: Bytes) -> ParseResult<(Bytes, ())> {
     if !found {
         let code = nom::error::ErrorKind::TakeWhile1;
 
-        return Err(nom::Err::Error(nom::error::Error::new(input, code)));
+        return Err(nom::Err::Error(Error::new(input, code)));
     };
 
     input.advance(2);
@@ -260,18 +634,36 @@ fn clrf1(mut input: Bytes) -> ParseResult<(Bytes, ())> {
 
 #[inline]
 fn digit1(input: Bytes) -> ParseResult<(Bytes, usize)> {
-    let cond = |c| matches!(c, b'0'..=b'9');
+    let cond = |c: u8| c.is_ascii_digit();
 
     let (input, found) = take_while1(cond)(input)?;
 
-    let (d, _) = FromRadix10::from_radix_10(&found);
+    match FromRadix10Checked::from_radix_10_checked(&found) {
+        (Some(d), _) => Ok((input, d)),
+        (None, _) => Err(failure(ProtocolError::NumberOverflow)),
+    }
+}
+
+// a whole field read as a number, as the tokenizer does, so PUB/MSG
+// reject `11x` instead of reading 11 whatever `simd` is set to
+#[inline]
+fn number1(input: Bytes) -> ParseResult<(Bytes, usize)> {
+    let (rest, found) = ident1(input.clone())?;
 
-    Ok((input, d))
+    match tokenizer::parse_usize(&found) {
+        Ok(d) => Ok((rest, d)),
+        Err(ProtocolError::NumberOverflow) => Err(failure(ProtocolError::NumberOverflow)),
+        Err(_) => {
+            let code = nom::error::ErrorKind::Digit;
+
+            Err(nom::Err::Error(Error::new(input, code)))
+        }
+    }
 }
 
 #[inline]
 fn ident1(input: Bytes) -> ParseResult<(Bytes, Bytes)> {
-    take_while1(|c| c != b' ')(input)
+    take_while1(|c| !matches!(c, b' ' | b'\t'))(input)
 }
 
 #[inline]
@@ -283,7 +675,7 @@ fn take_while1(cond: impl Fn(u8) -> bool) -> impl Fn(Bytes) -> ParseResult<(Byte
             0 => {
                 let code = nom::error::ErrorKind::TakeWhile1;
 
-                Err(nom::Err::Error(nom::error::Error::new(input, code)))
+                Err(nom::Err::Error(Error::new(input, code)))
             }
             _ => {
                 let found = input.split_to(at);
@@ -303,7 +695,7 @@ fn skip_while1(cond: impl Fn(u8) -> bool) -> impl Fn(Bytes) -> ParseResult<(Byte
             0 => {
                 let code = nom::error::ErrorKind::TakeWhile1;
 
-                Err(nom::Err::Error(nom::error::Error::new(input, code)))
+                Err(nom::Err::Error(Error::new(input, code)))
             }
             _ => {
                 input.advance(cnt);
@@ -318,10 +710,11 @@ fn skip_while1(cond: impl Fn(u8) -> bool) -> impl Fn(Bytes) -> ParseResult<(Byte
 mod test {
     use bytes::{Bytes, BytesMut};
 
+    use crate::error::ProtocolError;
     use crate::parser::ParseResult;
 
-    use super::message::{self, Message};
-    use super::{cl1, parse};
+    use super::message::{self, json::Json, Message};
+    use super::{bench, cl1, parse, parse_with, Error, Options, ParseMode};
 
     use super::{skip_while1, take_while1};
 
@@ -329,9 +722,18 @@ mod test {
     fn test_cl1() {
         let mut input = BytesMut::from("-ERR 'Maximum Connections Exceeded'\r\n");
 
-        let result = cl1(&mut input);
+        let head = cl1(&mut input);
 
-        assert!(result.is_ok());
+        assert_eq!(
+            Some(Bytes::from("-ERR 'Maximum Connections Exceeded'\r\n")),
+            head
+        );
+        assert!(input.is_empty());
+
+        let mut input = BytesMut::from("PING\r");
+
+        assert_eq!(None, cl1(&mut input));
+        assert_eq!(5, input.len());
     }
 
     #[test]
@@ -349,7 +751,7 @@ mod test {
 
             let code = nom::error::ErrorKind::TakeWhile1;
             assert_eq!(
-                Err(nom::Err::Error(nom::error::Error::new(input.clone(), code))),
+                Err(nom::Err::Error(Error::new(input.clone(), code))),
                 skip_while1(|c| matches!(c, b' ' | b'\t'))(input)
             );
         }
@@ -370,7 +772,7 @@ mod test {
 
             let code = nom::error::ErrorKind::TakeWhile1;
             assert_eq!(
-                Err(nom::Err::Error(nom::error::Error::new(input.clone(), code))),
+                Err(nom::Err::Error(Error::new(input.clone(), code))),
                 take_while1(|c| matches!(c, b' ' | b'\t'))(input)
             );
         }
@@ -449,11 +851,49 @@ mod test {
                 Ok(Message::Err(Payload::Unknown("unknown error".into()))),
                 "-ERR 'unknown error'",
             ),
+            (
+                Ok(Message::Err(Payload::PermissionsViolationForPublishTo(
+                    "\"foo.>\"".into(),
+                ))),
+                "-ERR 'Permissions Violation for Publish to \"foo.>\"'",
+            ),
+            (
+                Ok(Message::Err(Payload::PermissionsViolationForSubscription(
+                    "\"$JS.API.>\" using queue \"q'1\"".into(),
+                ))),
+                "-ERR 'Permissions Violation for Subscription to \"$JS.API.>\" using queue \"q'1\"'",
+            ),
+            (
+                Ok(Message::Err(Payload::Unknown(
+                    "nats: tls: failed to verify certificate for nats://127.0.0.1:4222/".into(),
+                ))),
+                "-ERR 'nats: tls: failed to verify certificate for nats://127.0.0.1:4222/'",
+            ),
+            (
+                Ok(Message::Err(Payload::Unknown("  leading and trailing  ".into()))),
+                "-ERR '  leading and trailing  '",
+            ),
+            (Ok(Message::Err(Payload::Unknown("".into()))), "-ERR ''"),
+            (
+                Ok(Message::Err(Payload::Unknown("unquoted: error/text".into()))),
+                "-ERR unquoted: error/text",
+            ),
+            (
+                Ok(Message::Err(Payload::Unknown("'not closed".into()))),
+                "-ERR 'not closed",
+            ),
         ];
 
         for (result, raw) in cases {
             assert_eq!(*result, parse(Bytes::from(*raw)));
         }
+
+        assert_eq!(
+            Ok(Message::Err(Payload::Unknown(Bytes::from_static(
+                b"\xff\xfe"
+            )))),
+            parse(Bytes::from_static(b"-ERR '\xff\xfe'"))
+        );
     }
 
     #[test]
@@ -486,6 +926,36 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_bench_parsers_agree() {
+        for raw in &[
+            "PUB FOO 11",
+            "PUB FOO INBOX.1 11",
+            "MSG FOO 9 11",
+            "MSG FOO 9 INBOX.1 11",
+            "PUB FOO 99999999999999999999999",
+            "MSG FOO",
+            "PUB FOO 11x",
+            "PUB FOO INBOX.1 1x",
+            "PUB FOO 1x 11",
+            "PUB\tFOO   11",
+            "PUB FOO INBOX.1 11 12",
+            "PUB FOO -1",
+            "MSG FOO 9x 11",
+            "MSG FOO 9 11x",
+            "MSG FOO 9 INBOX.1 11 12",
+        ] {
+            let line = Bytes::from(*raw);
+
+            assert_eq!(bench::nom(line.clone()), bench::memchr(line), "{}", raw);
+        }
+
+        // the lenient decoder rejects them whichever parser `simd` picks
+        for raw in &["PUB FOO 11x", "MSG FOO 9x 11", "PUB FOO INBOX.1 11 12"] {
+            assert!(parse(Bytes::from(*raw)).is_err(), "{}", raw);
+        }
+    }
+
     #[test]
     fn parse_message() {
         use message::message::Payload;
@@ -578,4 +1048,180 @@ mod test {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn parse_lazy_json() {
+        let options = Options {
+            lazy_json: true,
+            ..Default::default()
+        };
+
+        let json = "{\"verbose\":false,\"pedantic\":false,\"lang\":\"rust\"}";
+
+        assert_eq!(
+            Ok(Message::RawConnect(Json::new(Bytes::from(json)))),
+            parse_with(Bytes::from(format!("CONNECT {}", json)), &options)
+        );
+
+        // schema mismatches don't matter until the json is decoded
+        assert_eq!(
+            Ok(Message::RawInfo(Json::new(Bytes::from("{\"port\":\"x\"}")))),
+            parse_with(Bytes::from("INFO {\"port\":\"x\"}"), &options)
+        );
+    }
+
+    #[test]
+    fn parse_hpublish() {
+        use message::hpublish::Payload;
+
+        let cases: &[(ParseResult<Message>, &str)] = &[
+            (
+                Ok(Message::HPublish(Payload {
+                    subject: Bytes::from("FRONT.DOOR"),
+                    reply_to: Some(Bytes::from("JOKE.22")),
+                    header_size: 45,
+                    total_size: 51,
+                    headers: None,
+                    payload: None,
+                })),
+                "HPUB FRONT.DOOR JOKE.22 45 51",
+            ),
+            (
+                Ok(Message::HPublish(Payload {
+                    subject: Bytes::from("FRONT.DOOR"),
+                    reply_to: None,
+                    header_size: 45,
+                    total_size: 51,
+                    headers: None,
+                    payload: None,
+                })),
+                "HPUB FRONT.DOOR 45 51",
+            ),
+        ];
+
+        for (result, raw) in cases {
+            assert_eq!(*result, parse(Bytes::from(*raw)));
+        }
+    }
+
+    #[test]
+    fn parse_hmessage() {
+        use message::hmessage::Payload;
+
+        let cases: &[(ParseResult<Message>, &str)] = &[
+            (
+                Ok(Message::HMessage(Payload {
+                    subject: Bytes::from("FOO.BAR"),
+                    sid: 9,
+                    reply_to: Some(Bytes::from("BAZ.69")),
+                    header_size: 34,
+                    total_size: 45,
+                    headers: None,
+                    payload: None,
+                })),
+                "HMSG FOO.BAR 9 BAZ.69 34 45",
+            ),
+            (
+                Ok(Message::HMessage(Payload {
+                    subject: Bytes::from("FOO.BAR"),
+                    sid: 9,
+                    reply_to: None,
+                    header_size: 34,
+                    total_size: 45,
+                    headers: None,
+                    payload: None,
+                })),
+                "HMSG FOO.BAR 9 34 45",
+            ),
+        ];
+
+        for (result, raw) in cases {
+            assert_eq!(*result, parse(Bytes::from(*raw)));
+        }
+    }
+
+    #[test]
+    fn parse_overflow() {
+        let overflow = Err(nom::Err::Failure(Error::Protocol(
+            ProtocolError::NumberOverflow,
+        )));
+
+        for raw in &[
+            "PUB foo 99999999999999999999999",
+            "PUB foo bar 18446744073709551616",
+            "MSG foo 99999999999999999999999 1",
+            "MSG foo 1 99999999999999999999999",
+            "SUB foo 99999999999999999999999",
+            "UNSUB 99999999999999999999999",
+            "UNSUB 1 99999999999999999999999",
+            "HPUB foo 1 99999999999999999999999",
+            "HMSG foo 1 bar 99999999999999999999999 1",
+        ] {
+            assert_eq!(overflow, parse(Bytes::from(*raw)), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn parse_header_size_exceeds_total() {
+        let result = Err(nom::Err::Failure(Error::Protocol(
+            ProtocolError::HeaderSizeExceedsTotal {
+                header_size: 12,
+                total_size: 10,
+            },
+        )));
+
+        assert_eq!(result, parse(Bytes::from("HPUB foo 12 10")));
+        assert_eq!(result, parse(Bytes::from("HMSG foo 1 bar 12 10")));
+    }
+
+    #[test]
+    fn parse_strict() {
+        let strict = Options {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+
+        let valid = &[
+            "PUB FOO BAR 11",
+            "MSG FOO 9 11",
+            "HPUB FOO 12 15",
+            "SUB FOO G1 1",
+            "UNSUB 1 5",
+            "PING",
+            "+OK",
+            "-ERR 'Unknown Protocol Operation'",
+            "INFO {\"server_id\":\"id\", \"version\":\"2.9.0\",\"go\":\"go\",\"host\":\"h\",\"port\":1}",
+        ];
+
+        for raw in valid {
+            assert!(parse_with(Bytes::from(*raw), &strict).is_ok(), "{}", raw);
+        }
+
+        let invalid = &[
+            // lowercase ops
+            "pub FOO 11",
+            "Ping",
+            // runs of spaces and tabs
+            "PUB  FOO 11",
+            "PUB FOO\t11",
+            "SUB FOO 1 ",
+            "-ERR  'Stale Connection'",
+            // trailing garbage
+            "SUB FOO 1x",
+            "SUB FOO G1 1 extra",
+            "UNSUB 1 5 extra",
+            "UNSUB 1x",
+            "PING PONG",
+            "-ERR 'Stale Connection' extra",
+            // unquoted error text
+            "-ERR Stale Connection",
+        ];
+
+        for raw in invalid {
+            // lenient mode accepts all of them
+            assert!(parse(Bytes::from(*raw)).is_ok(), "{}", raw);
+
+            assert!(parse_with(Bytes::from(*raw), &strict).is_err(), "{}", raw);
+        }
+    }
 }
@@ -0,0 +1,739 @@
+use std::io;
+
+use bytes::{BufMut, Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+
+use super::codec::{take_body, Body, State};
+use super::error::ProtocolError;
+use super::message::put_usize;
+use super::parser;
+use super::tokenizer;
+
+/// Frame of the server to server route protocol.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteMessage {
+    Info(Info),
+    Connect(Connect),
+    Ping,
+    Pong,
+    Ok,
+    // text without the quotes, byte for byte
+    Err(Bytes),
+    // RS+
+    Subscribe(Subscription),
+    // RS-
+    Unsubscribe(Subscription),
+    // RMSG, HMSG when `headers` is set
+    Message(RoutedMessage),
+}
+
+/// Interest of the remote server in `subject`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Subscription {
+    pub account: Bytes,
+    pub subject: Bytes,
+    pub queue_group: Option<Bytes>,
+    // number of queue subscribers, RS+ only
+    pub weight: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RoutedMessage {
+    pub account: Bytes,
+    pub subject: Bytes,
+    pub reply_to: Option<Bytes>,
+    // queue groups the remote server may deliver to
+    pub queue_groups: Vec<Bytes>,
+    pub headers: Option<Bytes>,
+    pub payload: Bytes,
+}
+
+/// Route INFO, the fields not listed here are kept in `extra`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Info {
+    pub server_id: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub host: String,
+    #[serde(default)]
+    pub port: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster: Option<String>,
+    #[serde(default)]
+    pub headers: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_urls: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Route CONNECT, the fields not listed here are kept in `extra`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Connect {
+    #[serde(default)]
+    pub echo: bool,
+    #[serde(default)]
+    pub verbose: bool,
+    #[serde(default)]
+    pub pedantic: bool,
+    #[serde(default)]
+    pub tls_required: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pass: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub cluster: String,
+    #[serde(default)]
+    pub headers: bool,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Codec of route connections, the client protocol is left to [`crate::Codec`].
+///
+/// Account pinned routes, which leave out the account, aren't supported.
+#[derive(Debug, Default)]
+pub struct RouteCodec {
+    state: State,
+    // RMSG/HMSG waiting for its payload, with the header size of HMSG
+    message: Option<(RoutedMessage, Option<usize>)>,
+}
+
+impl RouteCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Info,
+    Connect,
+    Ping,
+    Pong,
+    Ok,
+    Err,
+    Subscribe,
+    Unsubscribe,
+    Message,
+    HMessage,
+}
+
+const OPS: [(&[u8], Op); 10] = [
+    (b"RMSG", Op::Message),
+    (b"HMSG", Op::HMessage),
+    (b"RS+", Op::Subscribe),
+    (b"RS-", Op::Unsubscribe),
+    (b"PING", Op::Ping),
+    (b"PONG", Op::Pong),
+    (b"INFO", Op::Info),
+    (b"CONNECT", Op::Connect),
+    (b"+OK", Op::Ok),
+    (b"-ERR", Op::Err),
+];
+
+enum Line {
+    Complete(RouteMessage),
+    // RMSG/HMSG, header size and total size
+    Body(RoutedMessage, Option<usize>, usize),
+}
+
+impl tokio_util::codec::Decoder for RouteCodec {
+    type Item = RouteMessage;
+
+    type Error = io::Error;
+
+    fn decode(&mut self, input: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            match self.state {
+                State::Message => {
+                    let line = match parser::cl1(input) {
+                        Some(head) => head.slice(..head.len() - 2),
+                        None => return Ok(None),
+                    };
+
+                    match parse(line)? {
+                        Line::Complete(message) => break Ok(Some(message)),
+                        Line::Body(message, header_size, total_size) => {
+                            self.message = Some((message, header_size));
+                            self.state = State::Payload(total_size);
+                        }
+                    }
+                }
+                State::Payload(size) => {
+                    break match take_body(input, size) {
+                        Body::Complete(mut body) => {
+                            let (mut message, header_size) = self.message.take().unwrap();
+
+                            if let Some(header_size) = header_size {
+                                message.headers = Some(body.split_to(header_size));
+                            }
+                            message.payload = body;
+
+                            self.state = State::Message;
+
+                            Ok(Some(RouteMessage::Message(message)))
+                        }
+                        Body::Partial => Ok(None),
+                        Body::Mismatch => Err(ProtocolError::PayloadSizeMismatch {
+                            subject: self
+                                .message
+                                .as_ref()
+                                .map(|(m, _)| m.subject.clone())
+                                .unwrap_or_default(),
+                            size,
+                        }
+                        .into()),
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl tokio_util::codec::Encoder<RouteMessage> for RouteCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: RouteMessage, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        item.validate()?;
+        item.encode(dst)
+    }
+}
+
+fn parse(line: Bytes) -> Result<Line, ProtocolError> {
+    let invalid = ProtocolError::InvalidControlLine;
+
+    let (op, args) = match line.iter().position(|c| matches!(c, b' ' | b'\t')) {
+        Some(idx) => (&line[..idx], line.slice(idx..)),
+        None => (&line[..], Bytes::new()),
+    };
+
+    let op = OPS
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(op))
+        .map(|(_, op)| *op)
+        .ok_or(invalid.clone())?;
+
+    let message = match op {
+        Op::Message => return parse_message(&args, false),
+        Op::HMessage => return parse_message(&args, true),
+        Op::Subscribe | Op::Unsubscribe => {
+            let mut fields = [&[][..]; 4];
+            let slice = |field: &[u8]| args.slice_ref(field);
+
+            let subscription = match (op, tokenizer::split(&args, &mut fields)) {
+                (_, Some(2)) => Subscription {
+                    account: slice(fields[0]),
+                    subject: slice(fields[1]),
+                    ..Default::default()
+                },
+                (Op::Subscribe, Some(4)) => Subscription {
+                    account: slice(fields[0]),
+                    subject: slice(fields[1]),
+                    queue_group: Some(slice(fields[2])),
+                    weight: Some(tokenizer::parse_usize(fields[3])?),
+                },
+                (Op::Unsubscribe, Some(3)) => Subscription {
+                    account: slice(fields[0]),
+                    subject: slice(fields[1]),
+                    queue_group: Some(slice(fields[2])),
+                    weight: None,
+                },
+                _ => return Err(invalid),
+            };
+
+            match op {
+                Op::Subscribe => RouteMessage::Subscribe(subscription),
+                _ => RouteMessage::Unsubscribe(subscription),
+            }
+        }
+        Op::Ping => RouteMessage::Ping,
+        Op::Pong => RouteMessage::Pong,
+        Op::Ok => RouteMessage::Ok,
+        Op::Err => {
+            let text = args.slice_ref(args.trim_ascii());
+
+            // as the client parser does, the last quote closes the text and
+            // the unquoted form is taken as is
+            match parser::quoted(text.clone()) {
+                Ok((_, text)) => RouteMessage::Err(text),
+                Err(_) => RouteMessage::Err(text),
+            }
+        }
+        Op::Info => RouteMessage::Info(serde_json::from_slice(&args).map_err(|_| invalid)?),
+        Op::Connect => RouteMessage::Connect(serde_json::from_slice(&args).map_err(|_| invalid)?),
+    };
+
+    Ok(Line::Complete(message))
+}
+
+// RMSG <account> <subject> [<reply> | + <reply> <queue>... | | <queue>...] <#bytes>
+// HMSG <account> <subject> [...] <#header bytes> <#total bytes>
+fn parse_message(args: &Bytes, headers: bool) -> Result<Line, ProtocolError> {
+    let invalid = ProtocolError::InvalidControlLine;
+
+    let fields: Vec<&[u8]> = tokenizer::fields(args).collect();
+    let sizes = if headers { 2 } else { 1 };
+
+    if fields.len() < 2 + sizes {
+        return Err(invalid);
+    }
+
+    let (fields, sizes) = fields.split_at(fields.len() - sizes);
+
+    let (reply_to, queue_groups) = match &fields[2..] {
+        [] => (None, &[][..]),
+        [b"+", reply_to, queue_groups @ ..] => (Some(*reply_to), queue_groups),
+        [b"|", queue_groups @ ..] => (None, queue_groups),
+        [reply_to] => (Some(*reply_to), &[][..]),
+        _ => return Err(invalid),
+    };
+
+    let (header_size, total_size) = match sizes {
+        [total_size] => (None, tokenizer::parse_usize(total_size)?),
+        [header_size, total_size] => {
+            let header_size = tokenizer::parse_usize(header_size)?;
+            let total_size = tokenizer::parse_usize(total_size)?;
+
+            if header_size > total_size {
+                return Err(ProtocolError::HeaderSizeExceedsTotal {
+                    header_size,
+                    total_size,
+                });
+            }
+
+            (Some(header_size), total_size)
+        }
+        _ => return Err(invalid),
+    };
+
+    let message = RoutedMessage {
+        account: args.slice_ref(fields[0]),
+        subject: args.slice_ref(fields[1]),
+        reply_to: reply_to.map(|reply_to| args.slice_ref(reply_to)),
+        queue_groups: queue_groups.iter().map(|q| args.slice_ref(q)).collect(),
+        headers: None,
+        payload: Bytes::new(),
+    };
+
+    Ok(Line::Body(message, header_size, total_size))
+}
+
+// non-empty and free of whitespace, a token can't carry a field separator
+fn is_token(token: &[u8]) -> bool {
+    !token.is_empty() && !token.iter().any(u8::is_ascii_whitespace)
+}
+
+impl RouteMessage {
+    /// Rejects fields that would corrupt the control line.
+    pub fn validate(&self) -> Result<(), ProtocolError> {
+        let (account, subject) = match self {
+            Self::Subscribe(s) | Self::Unsubscribe(s) => {
+                if let Some(queue_group) = s.queue_group.as_ref().filter(|q| !is_token(q)) {
+                    return Err(ProtocolError::InvalidQueueGroup(queue_group.clone()));
+                }
+                // queue interest always carries its weight
+                if matches!(self, Self::Subscribe(_))
+                    && s.queue_group.is_some()
+                    && s.weight.is_none()
+                {
+                    return Err(ProtocolError::InvalidControlLine);
+                }
+
+                (&s.account, &s.subject)
+            }
+            Self::Message(m) => {
+                if let Some(reply_to) = m.reply_to.as_ref().filter(|r| !is_token(r)) {
+                    return Err(ProtocolError::InvalidReplyTo(reply_to.clone()));
+                }
+                if let Some(queue_group) = m.queue_groups.iter().find(|q| !is_token(q)) {
+                    return Err(ProtocolError::InvalidQueueGroup(queue_group.clone()));
+                }
+
+                (&m.account, &m.subject)
+            }
+            // sent quoted on a single line
+            Self::Err(text) => {
+                return match text.iter().any(|c| matches!(c, b'\r' | b'\n')) {
+                    true => Err(ProtocolError::InvalidErrorText(
+                        String::from_utf8_lossy(text).into_owned(),
+                    )),
+                    false => Ok(()),
+                };
+            }
+            _ => return Ok(()),
+        };
+
+        if !is_token(account) {
+            return Err(ProtocolError::InvalidControlLine);
+        }
+        if !is_token(subject) {
+            return Err(ProtocolError::InvalidSubject(subject.clone()));
+        }
+
+        Ok(())
+    }
+
+    pub fn encode(&self, dst: &mut BytesMut) -> Result<(), io::Error> {
+        match self {
+            Self::Info(info) => encode_json(dst, b"INFO ", info)?,
+            Self::Connect(connect) => encode_json(dst, b"CONNECT ", connect)?,
+            Self::Ping => dst.put_slice(b"PING"),
+            Self::Pong => dst.put_slice(b"PONG"),
+            Self::Ok => dst.put_slice(b"+OK"),
+            Self::Err(text) => {
+                dst.put_slice(b"-ERR '");
+                dst.put_slice(text);
+                dst.put_u8(b'\'');
+            }
+            Self::Subscribe(s) | Self::Unsubscribe(s) => {
+                let is_subscribe = matches!(self, Self::Subscribe(_));
+
+                dst.put_slice(if is_subscribe { b"RS+ " } else { b"RS- " });
+                dst.put_slice(&s.account);
+                dst.put_u8(b' ');
+                dst.put_slice(&s.subject);
+
+                if let Some(queue_group) = &s.queue_group {
+                    dst.put_u8(b' ');
+                    dst.put_slice(queue_group);
+
+                    if let (true, Some(weight)) = (is_subscribe, s.weight) {
+                        dst.put_u8(b' ');
+                        put_usize(dst, weight);
+                    }
+                }
+            }
+            Self::Message(m) => {
+                let header_size = m.headers.as_ref().map(Bytes::len);
+                let total_size = header_size.unwrap_or_default() + m.payload.len();
+
+                dst.put_slice(match header_size {
+                    Some(_) => b"HMSG ",
+                    None => b"RMSG ",
+                });
+                dst.put_slice(&m.account);
+                dst.put_u8(b' ');
+                dst.put_slice(&m.subject);
+
+                match (&m.reply_to, m.queue_groups.is_empty()) {
+                    (Some(reply_to), true) => {
+                        dst.put_u8(b' ');
+                        dst.put_slice(reply_to);
+                    }
+                    (Some(reply_to), false) => {
+                        dst.put_slice(b" + ");
+                        dst.put_slice(reply_to);
+                    }
+                    (None, false) => dst.put_slice(b" |"),
+                    (None, true) => {}
+                }
+
+                for queue_group in &m.queue_groups {
+                    dst.put_u8(b' ');
+                    dst.put_slice(queue_group);
+                }
+
+                if let Some(header_size) = header_size {
+                    dst.put_u8(b' ');
+                    put_usize(dst, header_size);
+                }
+
+                dst.put_u8(b' ');
+                put_usize(dst, total_size);
+                dst.put_slice(b"\r\n");
+
+                if let Some(headers) = &m.headers {
+                    dst.put_slice(headers);
+                }
+                dst.put_slice(&m.payload);
+            }
+        }
+
+        dst.put_slice(b"\r\n");
+
+        Ok(())
+    }
+}
+
+fn encode_json(dst: &mut BytesMut, header: &[u8], value: &impl Serialize) -> Result<(), io::Error> {
+    dst.put_slice(header);
+
+    serde_json::to_writer(dst.writer(), value).map_err(|_| io::Error::other("cannot encode json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Bytes, BytesMut};
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::{RouteCodec, RouteMessage, RoutedMessage, Subscription};
+    use crate::{Codec, ProtocolError};
+
+    fn decode_all(input: &str) -> Vec<RouteMessage> {
+        let mut input = BytesMut::from(input.as_bytes());
+        let mut codec = RouteCodec::new();
+        let mut messages = Vec::new();
+
+        while let Some(message) = codec.decode(&mut input).unwrap() {
+            messages.push(message);
+        }
+
+        assert!(input.is_empty());
+
+        messages
+    }
+
+    fn message(account: &'static str, subject: &'static str) -> RoutedMessage {
+        RoutedMessage {
+            account: Bytes::from(account),
+            subject: Bytes::from(subject),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let messages = decode_all(
+            "RS+ $G orders.*\r\nRS+ $G orders.eu workers 3\r\nrs- $G orders.*\r\nRS- $G orders.eu workers\r\n",
+        );
+
+        assert_eq!(
+            vec![
+                RouteMessage::Subscribe(Subscription {
+                    account: Bytes::from("$G"),
+                    subject: Bytes::from("orders.*"),
+                    ..Default::default()
+                }),
+                RouteMessage::Subscribe(Subscription {
+                    account: Bytes::from("$G"),
+                    subject: Bytes::from("orders.eu"),
+                    queue_group: Some(Bytes::from("workers")),
+                    weight: Some(3),
+                }),
+                RouteMessage::Unsubscribe(Subscription {
+                    account: Bytes::from("$G"),
+                    subject: Bytes::from("orders.*"),
+                    ..Default::default()
+                }),
+                RouteMessage::Unsubscribe(Subscription {
+                    account: Bytes::from("$G"),
+                    subject: Bytes::from("orders.eu"),
+                    queue_group: Some(Bytes::from("workers")),
+                    weight: None,
+                }),
+            ],
+            messages
+        );
+
+        for line in [
+            "RS+ $G\r\n",
+            "RS+ $G foo q\r\n",
+            "RS- $G foo q 1\r\n",
+            "RS+ $G foo q x\r\n",
+        ] {
+            let mut input = BytesMut::from(line.as_bytes());
+
+            assert!(RouteCodec::new().decode(&mut input).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_messages() {
+        let messages = decode_all(concat!(
+            "RMSG $G orders.eu 5\r\nhello\r\n",
+            "RMSG $G orders.eu _INBOX.1 5\r\nhello\r\n",
+            "RMSG $G orders.eu + _INBOX.1 q1 q2 5\r\nhello\r\n",
+            "RMSG $G orders.eu | q1 0\r\n\r\n",
+            "HMSG $G orders.eu _INBOX.1 12 17\r\nNATS/1.0\r\n\r\nhello\r\n",
+        ));
+
+        let expected = [
+            RoutedMessage {
+                payload: Bytes::from("hello"),
+                ..message("$G", "orders.eu")
+            },
+            RoutedMessage {
+                reply_to: Some(Bytes::from("_INBOX.1")),
+                payload: Bytes::from("hello"),
+                ..message("$G", "orders.eu")
+            },
+            RoutedMessage {
+                reply_to: Some(Bytes::from("_INBOX.1")),
+                queue_groups: vec![Bytes::from("q1"), Bytes::from("q2")],
+                payload: Bytes::from("hello"),
+                ..message("$G", "orders.eu")
+            },
+            RoutedMessage {
+                queue_groups: vec![Bytes::from("q1")],
+                ..message("$G", "orders.eu")
+            },
+            RoutedMessage {
+                reply_to: Some(Bytes::from("_INBOX.1")),
+                headers: Some(Bytes::from("NATS/1.0\r\n\r\n")),
+                payload: Bytes::from("hello"),
+                ..message("$G", "orders.eu")
+            },
+        ];
+
+        assert_eq!(
+            expected
+                .into_iter()
+                .map(RouteMessage::Message)
+                .collect::<Vec<_>>(),
+            messages
+        );
+
+        let mut input = BytesMut::from("RMSG $G orders.eu 3\r\nhello\r\n");
+
+        let e = RouteCodec::new().decode(&mut input).unwrap_err();
+
+        assert_eq!(
+            Some(&ProtocolError::PayloadSizeMismatch {
+                subject: Bytes::from("orders.eu"),
+                size: 3
+            }),
+            e.get_ref().and_then(|e| e.downcast_ref())
+        );
+
+        let mut input = BytesMut::from("HMSG $G orders.eu 20 17\r\n");
+
+        assert!(RouteCodec::new().decode(&mut input).is_err());
+    }
+
+    #[test]
+    fn test_partial() {
+        let mut codec = RouteCodec::new();
+        let mut input = BytesMut::from("RMSG $G orders.eu 5\r\nhel");
+
+        assert_eq!(None, codec.decode(&mut input).unwrap());
+
+        input.extend_from_slice(b"lo\r\nPING\r\nRS+ $G");
+
+        assert!(matches!(
+            codec.decode(&mut input).unwrap(),
+            Some(RouteMessage::Message(m)) if m.payload == "hello"
+        ));
+        assert_eq!(Some(RouteMessage::Ping), codec.decode(&mut input).unwrap());
+        assert_eq!(None, codec.decode(&mut input).unwrap());
+    }
+
+    #[test]
+    fn test_info_connect() {
+        let messages = decode_all(concat!(
+            "INFO {\"server_id\":\"NA1\",\"name\":\"n1\",\"version\":\"2.10.7\",\"host\":\"10.0.0.1\",\"port\":6222,\"cluster\":\"eu\",\"headers\":true,\"lnoc\":true}\r\n",
+            "CONNECT {\"echo\":true,\"verbose\":false,\"pedantic\":false,\"tls_required\":false,\"name\":\"NA2\",\"cluster\":\"eu\",\"headers\":true,\"dynamic\":true}\r\n",
+            "-ERR 'Authorization Violation'\r\n",
+        ));
+
+        let info = match &messages[0] {
+            RouteMessage::Info(info) => info,
+            message => panic!("{:?}", message),
+        };
+
+        assert_eq!("NA1", info.server_id);
+        assert_eq!(Some("eu"), info.cluster.as_deref());
+        assert_eq!(Some(&serde_json::Value::Bool(true)), info.extra.get("lnoc"));
+
+        let connect = match &messages[1] {
+            RouteMessage::Connect(connect) => connect,
+            message => panic!("{:?}", message),
+        };
+
+        assert_eq!("NA2", connect.name);
+        assert!(connect.headers);
+        assert_eq!(
+            Some(&serde_json::Value::Bool(true)),
+            connect.extra.get("dynamic")
+        );
+
+        assert_eq!(
+            RouteMessage::Err("Authorization Violation".into()),
+            messages[2]
+        );
+
+        let mut input = BytesMut::from(&b"-ERR 'can't route \xff'\r\n"[..]);
+
+        assert_eq!(
+            Some(RouteMessage::Err(Bytes::from_static(b"can't route \xff"))),
+            RouteCodec::new().decode(&mut input).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let input = concat!(
+            "INFO {\"server_id\":\"NA1\",\"version\":\"2.10.7\",\"host\":\"10.0.0.1\",\"port\":6222,\"headers\":true,\"lnoc\":true}\r\n",
+            "PING\r\n",
+            "RS+ $G orders.eu workers 3\r\n",
+            "RS- $G orders.eu workers\r\n",
+            "RMSG $G orders.eu + _INBOX.1 q1 q2 5\r\nhello\r\n",
+            "RMSG $G orders.eu | q1 5\r\nhello\r\n",
+            "HMSG $G orders.eu 12 17\r\nNATS/1.0\r\n\r\nhello\r\n",
+            "-ERR 'Unknown Protocol Operation'\r\n",
+            "-ERR 'can't route'\r\n",
+        );
+
+        let mut codec = RouteCodec::new();
+        let mut dst = BytesMut::new();
+
+        for message in decode_all(input) {
+            codec.encode(message, &mut dst).unwrap();
+        }
+
+        assert_eq!(input.as_bytes(), &dst[..]);
+
+        let mut bad = message("$G", "orders eu");
+
+        assert!(codec
+            .encode(RouteMessage::Message(bad.clone()), &mut dst)
+            .is_err());
+
+        bad.subject = Bytes::from("orders.eu");
+        bad.queue_groups = vec![Bytes::new()];
+
+        assert!(codec.encode(RouteMessage::Message(bad), &mut dst).is_err());
+
+        let err = RouteMessage::Err(Bytes::from("x'\r\nRS+ $G orders.*"));
+
+        assert_eq!(
+            Err(ProtocolError::InvalidErrorText(
+                "x'\r\nRS+ $G orders.*".into()
+            )),
+            err.validate()
+        );
+        assert!(codec.encode(err, &mut dst).is_err());
+
+        let subscription = Subscription {
+            account: Bytes::from("$G"),
+            subject: Bytes::from("orders.eu"),
+            queue_group: Some(Bytes::from("workers")),
+            weight: None,
+        };
+
+        assert!(codec
+            .encode(RouteMessage::Subscribe(subscription.clone()), &mut dst)
+            .is_err());
+
+        let mut dst = BytesMut::new();
+
+        codec
+            .encode(RouteMessage::Unsubscribe(subscription), &mut dst)
+            .unwrap();
+
+        assert_eq!(&b"RS- $G orders.eu workers\r\n"[..], &dst[..]);
+    }
+
+    #[test]
+    fn test_client_codec_unaffected() {
+        let mut input = BytesMut::from("RS+ $G orders.*\r\n");
+
+        assert!(Codec::new().decode(&mut input).is_err());
+    }
+}
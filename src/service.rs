@@ -0,0 +1,697 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
+use std::{error, fmt};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+use crate::message::{Headers, Message};
+use crate::name::is_name;
+use crate::time::{nanos, rfc3339};
+use crate::ProtocolError;
+
+pub const API_PREFIX: &str = "$SRV";
+pub const ERROR: &str = "Nats-Service-Error";
+pub const ERROR_CODE: &str = "Nats-Service-Error-Code";
+pub const DEFAULT_QUEUE_GROUP: &str = "q";
+
+const PING_RESPONSE: &str = "io.nats.micro.v1.ping_response";
+const INFO_RESPONSE: &str = "io.nats.micro.v1.info_response";
+const STATS_RESPONSE: &str = "io.nats.micro.v1.stats_response";
+
+/// Request/reply service answering the `$SRV` discovery requests.
+///
+/// Requests reach the caller through the subscriptions built here, the
+/// outcome of each is fed back with `record` for the `STATS` replies.
+#[derive(Debug, Clone)]
+pub struct Service {
+    name: String,
+    id: String,
+    version: String,
+    description: Option<String>,
+    metadata: BTreeMap<String, String>,
+    started: SystemTime,
+    endpoints: Vec<Endpoint>,
+    // sid of the first subscription, see `subscriptions`
+    first_sid: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    pub name: String,
+    pub subject: String,
+    pub queue_group: String,
+    pub metadata: BTreeMap<String, String>,
+    pub stats: Stats,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub num_requests: u64,
+    pub num_errors: u64,
+    pub last_error: Option<EndpointError>,
+    pub processing_time: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verb {
+    Ping,
+    Info,
+    Stats,
+}
+
+/// What a delivery to one of the service subscriptions asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    Discovery(Verb),
+    // index of the endpoint
+    Endpoint(usize),
+}
+
+impl Verb {
+    pub const ALL: [Verb; 3] = [Verb::Ping, Verb::Info, Verb::Stats];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ping => "PING",
+            Self::Info => "INFO",
+            Self::Stats => "STATS",
+        }
+    }
+
+    /// `$SRV.<verb>[.<name>[.<id>]]`, `id` is ignored without `name`.
+    pub fn subject(&self, name: Option<&str>, id: Option<&str>) -> String {
+        match (name, id) {
+            (Some(name), Some(id)) => format!("{}.{}.{}.{}", API_PREFIX, self.as_str(), name, id),
+            (Some(name), None) => format!("{}.{}.{}", API_PREFIX, self.as_str(), name),
+            (None, _) => format!("{}.{}", API_PREFIX, self.as_str()),
+        }
+    }
+}
+
+impl Service {
+    /// `id` has to be unique among the instances of the service.
+    pub fn new(
+        name: impl Into<String>,
+        id: impl Into<String>,
+        version: impl Into<String>,
+    ) -> Result<Self, ServiceError> {
+        let (name, id, version) = (name.into(), id.into(), version.into());
+
+        if !is_name(&name) {
+            return Err(ServiceError::InvalidName(name));
+        }
+        if !is_name(&id) {
+            return Err(ServiceError::InvalidId(id));
+        }
+        if !is_semver(&version) {
+            return Err(ServiceError::InvalidVersion(version));
+        }
+
+        Ok(Self {
+            name,
+            id,
+            version,
+            description: None,
+            metadata: BTreeMap::new(),
+            started: SystemTime::now(),
+            endpoints: Vec::new(),
+            first_sid: None,
+        })
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+
+        self
+    }
+
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn endpoints(&self) -> &[Endpoint] {
+        &self.endpoints
+    }
+
+    /// Adds an endpoint, subscribed in the `q` queue group unless another
+    /// one is set, and returns its index. `metadata` shows up in the `INFO`
+    /// and `STATS` replies.
+    pub fn add_endpoint(
+        &mut self,
+        name: impl Into<String>,
+        subject: impl Into<String>,
+        queue_group: Option<&str>,
+        metadata: BTreeMap<String, String>,
+    ) -> Result<usize, ServiceError> {
+        let (name, subject) = (name.into(), subject.into());
+        let queue_group = queue_group.unwrap_or(DEFAULT_QUEUE_GROUP);
+
+        if !is_name(&name) || self.endpoints.iter().any(|e| e.name == name) {
+            return Err(ServiceError::InvalidEndpoint(name));
+        }
+        let invalid = subject.split('.').any(str::is_empty)
+            || Message::queue_subscribe(subject.clone(), queue_group.to_string(), 1)
+                .validate()
+                .is_err();
+
+        if invalid {
+            return Err(ServiceError::InvalidEndpoint(subject));
+        }
+
+        self.endpoints.push(Endpoint {
+            name,
+            subject,
+            queue_group: queue_group.to_string(),
+            metadata,
+            stats: Stats::default(),
+        });
+
+        Ok(self.endpoints.len() - 1)
+    }
+
+    /// SUB frames of the discovery subjects followed by the endpoints,
+    /// using consecutive sids from `first_sid`.
+    pub fn subscriptions(&mut self, first_sid: usize) -> Vec<Message> {
+        self.first_sid = Some(first_sid);
+
+        let mut sid = first_sid..;
+        let mut messages = Vec::with_capacity(9 + self.endpoints.len());
+
+        for verb in Verb::ALL {
+            for subject in [
+                verb.subject(None, None),
+                verb.subject(Some(&self.name), None),
+                verb.subject(Some(&self.name), Some(&self.id)),
+            ] {
+                messages.push(Message::subscribe(subject, sid.next().unwrap_or_default()));
+            }
+        }
+
+        for endpoint in &self.endpoints {
+            messages.push(Message::queue_subscribe(
+                endpoint.subject.clone(),
+                endpoint.queue_group.clone(),
+                sid.next().unwrap_or_default(),
+            ));
+        }
+
+        messages
+    }
+
+    /// Route of a MSG/HMSG by the sid of its subscription, `None` for
+    /// other subscriptions.
+    pub fn route(&self, message: &Message) -> Option<Route> {
+        let idx = message.sid()?.checked_sub(self.first_sid?)?;
+
+        match idx {
+            0..=8 => Some(Route::Discovery(Verb::ALL[idx / 3])),
+            idx if idx - 9 < self.endpoints.len() => Some(Route::Endpoint(idx - 9)),
+            _ => None,
+        }
+    }
+
+    /// Reply to a discovery request.
+    pub fn discovery_reply(&self, verb: Verb, request: &Message) -> Result<Message, ServiceError> {
+        let body = match verb {
+            Verb::Ping => serde_json::to_vec(&self.ping()),
+            Verb::Info => serde_json::to_vec(&self.info()),
+            Verb::Stats => serde_json::to_vec(&self.stats()),
+        }
+        .map_err(|e| ServiceError::Json(e.to_string()))?;
+
+        Ok(Message::publish(reply_subject(request)?, body))
+    }
+
+    /// Counts a request handled by the endpoint at `idx`.
+    pub fn record(&mut self, idx: usize, elapsed: Duration, error: Option<&EndpointError>) {
+        let stats = match self.endpoints.get_mut(idx) {
+            Some(endpoint) => &mut endpoint.stats,
+            None => return,
+        };
+
+        stats.num_requests += 1;
+        stats.processing_time += elapsed;
+
+        if let Some(error) = error {
+            stats.num_errors += 1;
+            stats.last_error = Some(error.clone());
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for endpoint in &mut self.endpoints {
+            endpoint.stats = Stats::default();
+        }
+    }
+
+    pub fn ping(&self) -> PingResponse {
+        PingResponse {
+            kind: PING_RESPONSE.into(),
+            name: self.name.clone(),
+            id: self.id.clone(),
+            version: self.version.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    pub fn info(&self) -> InfoResponse {
+        InfoResponse {
+            kind: INFO_RESPONSE.into(),
+            name: self.name.clone(),
+            id: self.id.clone(),
+            version: self.version.clone(),
+            metadata: self.metadata.clone(),
+            description: self.description.clone().unwrap_or_default(),
+            endpoints: self
+                .endpoints
+                .iter()
+                .map(|e| EndpointInfo {
+                    name: e.name.clone(),
+                    subject: e.subject.clone(),
+                    queue_group: e.queue_group.clone(),
+                    metadata: e.metadata.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn stats(&self) -> StatsResponse {
+        StatsResponse {
+            kind: STATS_RESPONSE.into(),
+            name: self.name.clone(),
+            id: self.id.clone(),
+            version: self.version.clone(),
+            metadata: self.metadata.clone(),
+            started: rfc3339(self.started),
+            endpoints: self
+                .endpoints
+                .iter()
+                .map(|e| {
+                    let processing_time = nanos(e.stats.processing_time);
+
+                    EndpointStats {
+                        name: e.name.clone(),
+                        subject: e.subject.clone(),
+                        queue_group: e.queue_group.clone(),
+                        num_requests: e.stats.num_requests,
+                        num_errors: e.stats.num_errors,
+                        last_error: e
+                            .stats
+                            .last_error
+                            .as_ref()
+                            .map(ToString::to_string)
+                            .unwrap_or_default(),
+                        processing_time,
+                        average_processing_time: processing_time
+                            .checked_div(e.stats.num_requests)
+                            .unwrap_or_default(),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Reply to an endpoint request.
+pub fn respond(request: &Message, body: impl Into<Bytes>) -> Result<Message, ServiceError> {
+    Ok(Message::publish(reply_subject(request)?, body))
+}
+
+/// Reply carrying `error` in the `Nats-Service-Error` headers.
+pub fn respond_error(
+    request: &Message,
+    error: &EndpointError,
+    body: impl Into<Bytes>,
+) -> Result<Message, ServiceError> {
+    let mut headers = Headers::new();
+    headers
+        .insert(ERROR, error.description.as_str())
+        .insert(ERROR_CODE, error.code.to_string());
+
+    Ok(Message::hpublish(reply_subject(request)?, &headers, body)?)
+}
+
+fn reply_subject(request: &Message) -> Result<Bytes, ServiceError> {
+    match request {
+        Message::Message(_) | Message::HMessage(_) => request.reply_to().cloned(),
+        _ => None,
+    }
+    .ok_or(ServiceError::NoReplySubject)
+}
+
+// MAJOR.MINOR.PATCH[-prerelease][+build]
+fn is_semver(version: &str) -> bool {
+    let is_ident = |s: &str| {
+        !s.is_empty()
+            && s.split('.').all(|part| {
+                !part.is_empty() && part.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-')
+            })
+    };
+
+    let (version, build) = match version.split_once('+') {
+        Some((version, build)) => (version, Some(build)),
+        None => (version, None),
+    };
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (version, None),
+    };
+
+    let parts: Vec<&str> = core.split('.').collect();
+    let is_number = |s: &&str| {
+        !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit()) && (*s == "0" || !s.starts_with('0'))
+    };
+
+    parts.len() == 3
+        && parts.iter().all(is_number)
+        && pre.is_none_or(is_ident)
+        && build.is_none_or(is_ident)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PingResponse {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+    pub id: String,
+    pub version: String,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InfoResponse {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+    pub id: String,
+    pub version: String,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub endpoints: Vec<EndpointInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointInfo {
+    pub name: String,
+    pub subject: String,
+    pub queue_group: String,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsResponse {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+    pub id: String,
+    pub version: String,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    // RFC 3339
+    pub started: String,
+    #[serde(default)]
+    pub endpoints: Vec<EndpointStats>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointStats {
+    pub name: String,
+    pub subject: String,
+    pub queue_group: String,
+    pub num_requests: u64,
+    pub num_errors: u64,
+    // `<code>:<description>` of the last error, empty if none
+    #[serde(default)]
+    pub last_error: String,
+    // nanoseconds
+    pub processing_time: u64,
+    pub average_processing_time: u64,
+}
+
+/// Error returned by an endpoint handler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointError {
+    pub code: u16,
+    pub description: String,
+}
+
+impl EndpointError {
+    pub fn new(code: u16, description: impl Into<String>) -> Self {
+        Self {
+            code,
+            description: description.into(),
+        }
+    }
+
+    /// Error of a service reply, `None` for a successful one.
+    pub fn from_message(message: &Message) -> Option<Self> {
+        let headers = message.headers().ok()?;
+        let description = headers.get(ERROR)?;
+
+        Some(Self {
+            code: headers
+                .get(ERROR_CODE)
+                .and_then(|code| code.trim().parse().ok())
+                .unwrap_or_default(),
+            description: description.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for EndpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.code, self.description)
+    }
+}
+
+impl error::Error for EndpointError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServiceError {
+    InvalidName(String),
+    InvalidId(String),
+    InvalidVersion(String),
+    // endpoint name taken or invalid, or invalid endpoint subject
+    InvalidEndpoint(String),
+    NoReplySubject,
+    Json(String),
+    Protocol(ProtocolError),
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName(name) => write!(f, "invalid service name '{}'", name),
+            Self::InvalidId(id) => write!(f, "invalid service id '{}'", id),
+            Self::InvalidVersion(version) => write!(f, "invalid service version '{}'", version),
+            Self::InvalidEndpoint(endpoint) => write!(f, "invalid endpoint '{}'", endpoint),
+            Self::NoReplySubject => write!(f, "request without reply subject"),
+            Self::Json(e) => write!(f, "invalid discovery reply: {}", e),
+            Self::Protocol(e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for ServiceError {}
+
+impl From<ProtocolError> for ServiceError {
+    fn from(e: ProtocolError) -> Self {
+        Self::Protocol(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bytes::{Bytes, BytesMut};
+    use tokio_util::codec::Encoder;
+
+    use super::*;
+    use crate::test_support::delivery;
+    use crate::Codec;
+
+    fn service() -> Service {
+        let mut service = Service::new("orders", "N1", "1.2.0-rc.1")
+            .unwrap()
+            .with_description("order processing")
+            .with_metadata("region", "eu");
+
+        service
+            .add_endpoint("create", "orders.create", None, BTreeMap::new())
+            .unwrap();
+        service
+            .add_endpoint(
+                "status",
+                "orders.status.*",
+                Some("status"),
+                BTreeMap::from([("format".to_string(), "json".to_string())]),
+            )
+            .unwrap();
+
+        service
+    }
+
+    #[test]
+    fn test_validation() {
+        for version in ["0.0.1", "1.2.3-alpha.1", "1.2.3+build.5", "10.20.30-rc-1+b"] {
+            assert!(Service::new("svc", "id", version).is_ok(), "{}", version);
+        }
+        for version in ["1.2", "1.2.3.4", "01.2.3", "1.2.3-", "1.2.3-a..b", "v1.2.3"] {
+            assert_eq!(
+                Some(ServiceError::InvalidVersion(version.into())),
+                Service::new("svc", "id", version).err()
+            );
+        }
+
+        assert!(matches!(
+            Service::new("my.svc", "id", "1.0.0"),
+            Err(ServiceError::InvalidName(_))
+        ));
+
+        let mut service = service();
+
+        assert!(service
+            .add_endpoint("create", "orders.new", None, BTreeMap::new())
+            .is_err());
+        assert!(service
+            .add_endpoint("bad", "orders. new", None, BTreeMap::new())
+            .is_err());
+        assert!(service
+            .add_endpoint("bad", "orders..new", None, BTreeMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let mut service = service();
+        let mut codec = Codec::new();
+        let mut dst = BytesMut::new();
+
+        for message in service.subscriptions(10) {
+            codec.encode(message, &mut dst).unwrap();
+        }
+
+        assert_eq!(
+            &b"SUB $SRV.PING 10\r\nSUB $SRV.PING.orders 11\r\nSUB $SRV.PING.orders.N1 12\r\n\
+               SUB $SRV.INFO 13\r\nSUB $SRV.INFO.orders 14\r\nSUB $SRV.INFO.orders.N1 15\r\n\
+               SUB $SRV.STATS 16\r\nSUB $SRV.STATS.orders 17\r\nSUB $SRV.STATS.orders.N1 18\r\n\
+               SUB orders.create q 19\r\nSUB orders.status.* status 20\r\n"[..],
+            &dst[..]
+        );
+
+        let cases = [
+            (
+                "MSG $SRV.PING 10 _INBOX.1 0\r\n\r\n",
+                Some(Route::Discovery(Verb::Ping)),
+            ),
+            (
+                "MSG $SRV.INFO.orders 14 _INBOX.1 0\r\n\r\n",
+                Some(Route::Discovery(Verb::Info)),
+            ),
+            (
+                "MSG $SRV.STATS.orders.N1 18 _INBOX.1 0\r\n\r\n",
+                Some(Route::Discovery(Verb::Stats)),
+            ),
+            (
+                "MSG orders.status.7 20 _INBOX.1 0\r\n\r\n",
+                Some(Route::Endpoint(1)),
+            ),
+            ("MSG other 21 _INBOX.1 0\r\n\r\n", None),
+            ("MSG other 9 _INBOX.1 0\r\n\r\n", None),
+        ];
+
+        for (frame, expected) in cases {
+            assert_eq!(expected, service.route(&delivery(frame)), "{}", frame);
+        }
+    }
+
+    #[test]
+    fn test_discovery() {
+        let service = service();
+        let request = delivery("MSG $SRV.PING 10 _INBOX.1 0\r\n\r\n");
+
+        let reply = service.discovery_reply(Verb::Ping, &request).unwrap();
+
+        assert_eq!(Some(&Bytes::from("_INBOX.1")), reply.subject());
+        assert_eq!(
+            Some(&Bytes::from(
+                r#"{"type":"io.nats.micro.v1.ping_response","name":"orders","id":"N1","version":"1.2.0-rc.1","metadata":{"region":"eu"}}"#
+            )),
+            reply.body()
+        );
+
+        let reply = service.discovery_reply(Verb::Info, &request).unwrap();
+        let info: serde_json::Value = serde_json::from_slice(reply.body().unwrap()).unwrap();
+
+        assert_eq!("io.nats.micro.v1.info_response", info["type"]);
+        assert_eq!("order processing", info["description"]);
+        assert_eq!(
+            serde_json::json!({"name": "status", "subject": "orders.status.*", "queue_group": "status", "metadata": {"format": "json"}}),
+            info["endpoints"][1]
+        );
+
+        assert_eq!(
+            Err(ServiceError::NoReplySubject),
+            service.discovery_reply(Verb::Ping, &delivery("MSG $SRV.PING 10 0\r\n\r\n"))
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut service = service();
+
+        service.record(0, Duration::from_millis(3), None);
+        service.record(
+            0,
+            Duration::from_millis(5),
+            Some(&EndpointError::new(503, "db down")),
+        );
+
+        let stats = service.stats();
+
+        assert_eq!("io.nats.micro.v1.stats_response", stats.kind);
+        assert_eq!(2, stats.endpoints[0].num_requests);
+        assert_eq!(1, stats.endpoints[0].num_errors);
+        assert_eq!("503:db down", stats.endpoints[0].last_error);
+        assert_eq!(8_000_000, stats.endpoints[0].processing_time);
+        assert_eq!(4_000_000, stats.endpoints[0].average_processing_time);
+        assert_eq!(0, stats.endpoints[1].average_processing_time);
+
+        service.reset();
+
+        assert_eq!(0, service.stats().endpoints[0].num_requests);
+    }
+
+    #[test]
+    fn test_errors() {
+        let request = delivery("MSG orders.create 19 _INBOX.7 2\r\n{}\r\n");
+        let error = EndpointError::new(400, "missing order id");
+
+        let reply = respond_error(&request, &error, "").unwrap();
+
+        assert_eq!("HPUB _INBOX.7 80 80", reply.to_string());
+        assert_eq!(Some(error), EndpointError::from_message(&reply));
+
+        let reply = respond(&request, "{\"id\":1}").unwrap();
+
+        assert_eq!("PUB _INBOX.7 8", reply.to_string());
+        assert_eq!(None, EndpointError::from_message(&reply));
+    }
+}
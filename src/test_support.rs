@@ -0,0 +1,12 @@
+use bytes::BytesMut;
+use tokio_util::codec::Decoder;
+
+use super::codec::Codec;
+use super::message::Message;
+
+// a frame as the server would deliver it
+pub(crate) fn delivery(frame: &str) -> Message {
+    let mut buf = BytesMut::from(frame.as_bytes());
+
+    Codec::new().decode(&mut buf).unwrap().unwrap()
+}
@@ -0,0 +1,51 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// durations of the JSON APIs, saturating at `u64::MAX` nanoseconds
+#[inline]
+pub(crate) fn nanos(duration: Duration) -> u64 {
+    duration.as_nanos().min(u64::MAX as u128) as u64
+}
+
+// `2023-03-01T10:15:31.500000000Z`
+pub(crate) fn rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+
+    // civil from days, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:09}Z",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_nanos()
+    )
+}
+
+#[test]
+fn test_rfc3339() {
+    use std::time::Duration;
+
+    assert_eq!(
+        "2023-03-01T10:15:31.500000000Z",
+        rfc3339(UNIX_EPOCH + Duration::from_millis(1_677_665_731_500))
+    );
+    assert_eq!("1970-01-01T00:00:00.000000000Z", rfc3339(UNIX_EPOCH));
+    assert_eq!(
+        "2000-02-29T23:59:59.000000001Z",
+        rfc3339(UNIX_EPOCH + Duration::new(951_868_799, 1))
+    );
+}
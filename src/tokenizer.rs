@@ -0,0 +1,123 @@
+use memchr::memchr2;
+
+use super::error::ProtocolError;
+
+// Control line tokenizer for the hot PUB/MSG path and the route protocol.
+//
+// Field boundaries are found with `memchr2`, which is vectorized by memchr,
+// and numbers are parsed in a single checked pass instead of a `take_while`
+// followed by `from_radix_10`.
+
+/// Splits `line` on runs of spaces/tabs into `fields`.
+///
+/// Returns the number of fields, or `None` when there are more than `N`.
+#[inline]
+pub fn split<'a, const N: usize>(mut line: &'a [u8], fields: &mut [&'a [u8]; N]) -> Option<usize> {
+    let mut n = 0;
+
+    loop {
+        line = trim_start(line);
+
+        if line.is_empty() {
+            return Some(n);
+        }
+
+        if n == N {
+            return None;
+        }
+
+        let end = memchr2(b' ', b'\t', line).unwrap_or(line.len());
+
+        fields[n] = &line[..end];
+        n += 1;
+
+        line = &line[end..];
+    }
+}
+
+/// Fields of `line` separated by runs of spaces/tabs, for lines without
+/// an upper bound on the number of fields.
+#[cfg(feature = "route")]
+#[inline]
+pub fn fields(line: &[u8]) -> impl Iterator<Item = &[u8]> {
+    line.split(|c| matches!(c, b' ' | b'\t'))
+        .filter(|field| !field.is_empty())
+}
+
+/// Parses a non-empty run of ascii digits.
+#[inline]
+pub fn parse_usize(input: &[u8]) -> Result<usize, ProtocolError> {
+    if input.is_empty() {
+        return Err(ProtocolError::InvalidControlLine);
+    }
+
+    input.iter().try_fold(0usize, |acc, c| {
+        let d = c.wrapping_sub(b'0');
+
+        if d > 9 {
+            return Err(ProtocolError::InvalidControlLine);
+        }
+
+        acc.checked_mul(10)
+            .and_then(|acc| acc.checked_add(d as usize))
+            .ok_or(ProtocolError::NumberOverflow)
+    })
+}
+
+/// `input` without its leading spaces/tabs.
+#[inline]
+pub fn trim_start(input: &[u8]) -> &[u8] {
+    match input.iter().position(|c| !matches!(c, b' ' | b'\t')) {
+        Some(idx) => &input[idx..],
+        None => &input[input.len()..],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_usize, split, ProtocolError};
+
+    #[test]
+    fn test_split() {
+        let mut fields = [&[][..]; 4];
+
+        assert_eq!(Some(3), split(b" FOO.BAR \t9  11", &mut fields));
+        assert_eq!([&b"FOO.BAR"[..], b"9", b"11"], fields[..3]);
+
+        assert_eq!(Some(0), split(b" \t ", &mut fields));
+        assert_eq!(None, split(b"A B C D E", &mut fields));
+    }
+
+    #[cfg(feature = "route")]
+    #[test]
+    fn test_fields() {
+        use super::fields;
+
+        assert_eq!(
+            vec![&b"$G"[..], b"FOO", b"+", b"_INBOX.1", b"q1", b"q2", b"11"],
+            fields(b" $G FOO \t+ _INBOX.1 q1  q2 11 ").collect::<Vec<_>>()
+        );
+        assert_eq!(0, fields(b" \t ").count());
+    }
+
+    #[test]
+    fn test_parse_usize() {
+        assert_eq!(Ok(0), parse_usize(b"0"));
+        assert_eq!(Ok(1048576), parse_usize(b"1048576"));
+        assert_eq!(
+            Ok(usize::MAX),
+            parse_usize(usize::MAX.to_string().as_bytes())
+        );
+
+        let invalid = Err(ProtocolError::InvalidControlLine);
+
+        assert_eq!(invalid, parse_usize(b""));
+        assert_eq!(invalid, parse_usize(b"11x"));
+        assert_eq!(invalid, parse_usize(b"-1"));
+
+        let overflow = Err(ProtocolError::NumberOverflow);
+
+        assert_eq!(overflow, parse_usize(b"99999999999999999999999"));
+        assert_eq!(overflow, parse_usize(b"18446744073709551616"));
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::VecDeque;
+use std::io::{self, IoSlice};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::{ready, Sink};
+use tokio::io::AsyncWrite;
+
+use super::message::Message;
+
+const CLRF: &[u8] = b"\r\n";
+
+// payloads below this size are cheaper to copy than to chain
+const DEFAULT_THRESHOLD: usize = 4 * 1024;
+// flush before accepting more once this much is buffered
+const DEFAULT_BACKPRESSURE: usize = 128 * 1024;
+const MAX_IO_SLICES: usize = 64;
+
+/// Encoded frames as a chain of chunks.
+///
+/// Control lines and small payloads are copied into a shared buffer, large
+/// payloads are chained as separate `Bytes` so they reach `write_vectored`
+/// without being copied.
+#[derive(Debug)]
+pub struct Chunks {
+    chunks: VecDeque<Bytes>,
+    tail: BytesMut,
+    remaining: usize,
+    threshold: usize,
+    derive_sizes: bool,
+}
+
+impl Default for Chunks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Chunks {
+    pub fn new() -> Self {
+        Self::with_threshold(DEFAULT_THRESHOLD)
+    }
+
+    pub fn with_threshold(threshold: usize) -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            tail: BytesMut::new(),
+            remaining: 0,
+            // an empty chained body would make `chunk` return an empty slice
+            threshold: threshold.max(1),
+            derive_sizes: false,
+        }
+    }
+
+    /// Set the declared sizes of pushed messages from their body instead of
+    /// rejecting mismatches, as [`crate::Codec::with_derive_sizes`] does.
+    pub fn with_derive_sizes(mut self, derive_sizes: bool) -> Self {
+        self.derive_sizes = derive_sizes;
+
+        self
+    }
+
+    /// Encodes `message`, failing without buffering anything when it doesn't
+    /// pass [`Message::validate`].
+    pub fn push(&mut self, message: &Message) -> Result<(), io::Error> {
+        let mut derived = None;
+
+        if self.derive_sizes {
+            derived.insert(message.clone()).derive_sizes();
+        }
+
+        let message = derived.as_ref().unwrap_or(message);
+
+        // never put a frame on the wire the other side can't parse
+        message.validate()?;
+
+        let before = self.tail.len();
+
+        match message.body() {
+            Some(body) if body.len() >= self.threshold => {
+                message.encode_head(&mut self.tail)?;
+
+                let head = self.tail.split().freeze();
+
+                self.remaining += head.len() - before + body.len() + CLRF.len();
+
+                self.chunks.push_back(head);
+                self.chunks.push_back(body.clone());
+
+                self.tail.put_slice(CLRF);
+
+                return Ok(());
+            }
+            _ => message.encode(&mut self.tail)?,
+        };
+
+        self.remaining += self.tail.len() - before;
+
+        Ok(())
+    }
+}
+
+impl Buf for Chunks {
+    #[inline]
+    fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    #[inline]
+    fn chunk(&self) -> &[u8] {
+        match self.chunks.front() {
+            Some(chunk) => chunk,
+            None => &self.tail,
+        }
+    }
+
+    fn chunks_vectored<'a>(&'a self, dst: &mut [IoSlice<'a>]) -> usize {
+        let chunks = self.chunks.iter().map(|c| &c[..]);
+        let tail = Some(&self.tail[..]);
+
+        let mut n = 0;
+
+        for chunk in chunks.chain(tail).filter(|c| !c.is_empty()) {
+            if n == dst.len() {
+                break;
+            }
+
+            dst[n] = IoSlice::new(chunk);
+            n += 1;
+        }
+
+        n
+    }
+
+    fn advance(&mut self, mut cnt: usize) {
+        assert!(cnt <= self.remaining, "advance out of bounds");
+
+        self.remaining -= cnt;
+
+        while cnt > 0 {
+            let chunk = match self.chunks.front_mut() {
+                Some(chunk) => chunk,
+                None => {
+                    self.tail.advance(cnt);
+
+                    return;
+                }
+            };
+
+            if cnt < chunk.len() {
+                chunk.advance(cnt);
+
+                return;
+            }
+
+            cnt -= chunk.len();
+            self.chunks.pop_front();
+        }
+
+        // drop emptied chunks so `chunk` never returns an empty slice early
+        while matches!(self.chunks.front(), Some(c) if c.is_empty()) {
+            self.chunks.pop_front();
+        }
+    }
+}
+
+/// `Sink` of messages writing through `AsyncWrite::poll_write_vectored`.
+#[derive(Debug)]
+pub struct VectoredSink<W> {
+    inner: W,
+    chunks: Chunks,
+    backpressure: usize,
+}
+
+impl<W> VectoredSink<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            chunks: Chunks::new(),
+            backpressure: DEFAULT_BACKPRESSURE,
+        }
+    }
+
+    pub fn with_chunks(inner: W, chunks: Chunks) -> Self {
+        Self {
+            inner,
+            chunks,
+            backpressure: DEFAULT_BACKPRESSURE,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: AsyncWrite + Unpin> VectoredSink<W> {
+    fn poll_write_chunks(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        while self.chunks.has_remaining() {
+            let mut slices = [IoSlice::new(&[]); MAX_IO_SLICES];
+
+            let n = self.chunks.chunks_vectored(&mut slices);
+
+            let written = ready!(Pin::new(&mut self.inner).poll_write_vectored(cx, &slices[..n]))?;
+
+            if written == 0 {
+                let kind = io::ErrorKind::WriteZero;
+
+                return Poll::Ready(Err(io::Error::new(kind, "failed to write frame")));
+            }
+
+            self.chunks.advance(written);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> Sink<Message> for VectoredSink<W> {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        if this.chunks.remaining() >= this.backpressure {
+            ready!(this.poll_write_chunks(cx))?;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        self.get_mut().chunks.push(&item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_write_chunks(cx))?;
+
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_write_chunks(cx))?;
+
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, IoSlice};
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use bytes::{Buf, Bytes, BytesMut};
+    use futures::SinkExt;
+    use tokio::io::AsyncWrite;
+
+    use super::{Chunks, VectoredSink};
+    use crate::message::{publish, Message};
+
+    fn publish(payload: &'static [u8]) -> Message {
+        Message::Publish(publish::Payload {
+            subject: Bytes::from("FOO"),
+            reply_to: None,
+            payload_size: payload.len(),
+            payload: Some(Bytes::from_static(payload)),
+        })
+    }
+
+    // records every vectored write, accepting at most `limit` bytes per call
+    struct Writer {
+        written: Vec<u8>,
+        writes: Vec<usize>,
+        limit: usize,
+    }
+
+    impl AsyncWrite for Writer {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, io::Error>> {
+            self.poll_write_vectored(cx, &[IoSlice::new(buf)])
+        }
+
+        fn poll_write_vectored(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            bufs: &[IoSlice<'_>],
+        ) -> Poll<Result<usize, io::Error>> {
+            let this = self.get_mut();
+
+            let mut n = 0;
+            for buf in bufs {
+                let take = buf.len().min(this.limit - n);
+
+                this.written.extend_from_slice(&buf[..take]);
+                n += take;
+            }
+
+            this.writes.push(bufs.len());
+
+            Poll::Ready(Ok(n))
+        }
+
+        fn is_write_vectored(&self) -> bool {
+            true
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Result<(), io::Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_chunks() {
+        let mut chunks = Chunks::with_threshold(8);
+
+        chunks.push(&Message::Ping).expect("ok");
+        chunks.push(&publish(b"large payload")).expect("ok");
+        chunks.push(&publish(b"small")).expect("ok");
+
+        let mut slices = [IoSlice::new(&[]); 8];
+
+        // head (PING + PUB control line), payload, tail (CLRF + small PUB)
+        assert_eq!(3, chunks.chunks_vectored(&mut slices));
+        assert_eq!(&b"large payload"[..], &slices[1][..]);
+
+        let mut expected = BytesMut::new();
+
+        Message::Ping.encode(&mut expected).expect("ok");
+        publish(b"large payload").encode(&mut expected).expect("ok");
+        publish(b"small").encode(&mut expected).expect("ok");
+
+        assert_eq!(expected.len(), chunks.remaining());
+        assert_eq!(&expected[..], &chunks.copy_to_bytes(expected.len())[..]);
+        assert!(!chunks.has_remaining());
+    }
+
+    #[test]
+    fn test_chunks_zero_threshold() {
+        let mut chunks = Chunks::with_threshold(0);
+
+        chunks.push(&publish(b"")).expect("ok");
+        chunks.push(&publish(b"x")).expect("ok");
+
+        // the empty payload is copied, only `x` is chained
+        assert_eq!(2, chunks.chunks.len());
+
+        let mut expected = BytesMut::new();
+
+        publish(b"").encode(&mut expected).expect("ok");
+        publish(b"x").encode(&mut expected).expect("ok");
+
+        while chunks.has_remaining() {
+            assert!(!chunks.chunk().is_empty());
+
+            let n = chunks.chunk().len();
+            assert_eq!(&expected[..n], chunks.chunk());
+
+            expected.advance(n);
+            chunks.advance(n);
+        }
+
+        assert!(expected.is_empty());
+    }
+
+    #[test]
+    fn test_chunks_validate() {
+        let mut chunks = Chunks::new();
+
+        let mut message = publish(b"hello");
+        if let Message::Publish(p) = &mut message {
+            p.payload_size = 3;
+        }
+
+        assert!(chunks.push(&message).is_err());
+        assert!(chunks.push(&Message::publish("FOO BAR", "hello")).is_err());
+        assert!(!chunks.has_remaining());
+
+        let mut chunks = Chunks::new().with_derive_sizes(true);
+
+        chunks.push(&message).expect("ok");
+
+        let mut expected = BytesMut::new();
+
+        publish(b"hello").encode(&mut expected).expect("ok");
+
+        assert_eq!(&expected[..], chunks.chunk());
+    }
+
+    #[test]
+    fn test_sink() {
+        let writer = Writer {
+            written: vec![],
+            writes: vec![],
+            limit: 10,
+        };
+
+        let mut sink = VectoredSink::with_chunks(writer, Chunks::with_threshold(8));
+
+        futures::executor::block_on(async {
+            sink.feed(publish(b"large payload")).await.expect("ok");
+            sink.feed(Message::Pong).await.expect("ok");
+            assert!(sink.feed(Message::publish("FOO BAR", "x")).await.is_err());
+            sink.flush().await.expect("ok");
+        });
+
+        let mut expected = BytesMut::new();
+
+        publish(b"large payload").encode(&mut expected).expect("ok");
+        Message::Pong.encode(&mut expected).expect("ok");
+
+        let writer = sink.into_inner();
+
+        assert_eq!(&expected[..], &writer.written[..]);
+        assert!(writer.writes.iter().any(|n| *n > 1));
+    }
+}